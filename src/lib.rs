@@ -1,16 +1,19 @@
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder, dpi::PhysicalSize,
+    window::WindowBuilder,
 };
 
 #[cfg(target_arch="wasm32")]
 use wasm_bindgen::prelude::*;
 
+mod options;
 mod render;
-mod systems;
+mod session;
+pub mod systems;
 
-use systems::GameState;
+use options::Options;
+use session::Session;
 
 #[cfg_attr(target_arch="wasm32", wasm_bindgen(start))]
 pub fn run() {
@@ -23,11 +26,48 @@ pub fn run() {
         }
     }    
 
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            return
+        }
+    };
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let replay = match options.replay.as_deref().map(session::load_replay).transpose() {
+        Ok(replay) => replay,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            return
+        }
+    };
+    #[cfg(target_arch = "wasm32")]
+    let replay = None;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let settings = match options.pieces.as_deref().map(|path| session::load_pieces(path, options.settings.width)).transpose() {
+        Ok(Some(pieces)) => systems::settings::Settings { pieces: std::sync::Arc::new(pieces), ..options.settings },
+        Ok(None) => options.settings,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            return
+        }
+    };
+    #[cfg(target_arch = "wasm32")]
+    let settings = options.settings;
+
+    let mut session = match replay {
+        Some(replay) => Session::watch(replay),
+        None => Session::play(settings, options.seed)
+    };
+    let mut title = session.title();
+
     let event_loop = EventLoop::new();
 
     let window = WindowBuilder::new()
-        .with_title("Tetris")
-        .with_inner_size(PhysicalSize { width: 400, height: 800})
+        .with_title(&title)
+        .with_inner_size(render::window_size(session.state()))
         .with_resizable(false)
         .build(&event_loop)
         .unwrap();
@@ -35,8 +75,7 @@ pub fn run() {
     #[cfg(target_arch = "wasm32")] {
         // Winit prevents sizing with CSS, so we have to set
         // the size manually when on web.
-        use winit::dpi::PhysicalSize;
-        window.set_inner_size(PhysicalSize::new(400, 800));
+        window.set_inner_size(render::window_size(session.state()));
         
         use winit::platform::web::WindowExtWebSys;
         web_sys::window()
@@ -52,12 +91,21 @@ pub fn run() {
 
     let mut render_state = pollster::block_on(render::new(window));
 
-    let mut game_state = GameState::new();
+    let mut previous_time = instant::Instant::now();
 
     event_loop.run(move |event, _, control_flow| match event {
         Event::RedrawRequested(window_id) if window_id == render_state.window().id() => {
-            game_state.update();
-            render_state.update(&game_state);
+            let current_time = instant::Instant::now();
+            let elapsed_time = current_time.duration_since(previous_time).as_secs_f32();
+            previous_time = current_time;
+
+            session.update(elapsed_time);
+            render_state.update(session.state(), session.alpha());
+            // Restarting deals a new seed
+            if session.title() != title {
+                title = session.title();
+                render_state.window().set_title(&title);
+            }
             match render_state.render() {
                 Ok(_) => {},
                 Err(wgpu::SurfaceError::Lost) => render_state.resize(render_state.size()),
@@ -71,27 +119,26 @@ pub fn run() {
         Event::WindowEvent {
             window_id,
             ref event,
-        } if window_id == render_state.window().id() => if !game_state.input(event) {
-            match event {
-                WindowEvent::CloseRequested
-                | WindowEvent::KeyboardInput {
-                    input:
-                        KeyboardInput {
-                            state: ElementState::Pressed,
-                            virtual_keycode: Some(VirtualKeyCode::Escape),
-                            ..
-                        },
-                    ..
-                } => *control_flow = ControlFlow::Exit,
-                WindowEvent::Resized(physical_size) => {
-                    render_state.resize(*physical_size);
-                },
-                WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                    render_state.resize(**new_inner_size);
-                },
-                _ => {}
-            }
+        } if window_id == render_state.window().id() && !session.input(event) => match event {
+            WindowEvent::CloseRequested
+            | WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Escape),
+                        ..
+                    },
+                ..
+            } => *control_flow = ControlFlow::Exit,
+            WindowEvent::Resized(physical_size) => {
+                render_state.resize(*physical_size);
+            },
+            WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                render_state.resize(**new_inner_size);
+            },
+            _ => {}
         },
+        Event::LoopDestroyed => session.finish(),
         _ => {}
     });
 }
\ No newline at end of file
@@ -0,0 +1,118 @@
+use std::ops::RangeInclusive;
+
+use anyhow::*;
+
+use crate::systems::{
+    mode::{Mode, DIG_LINES, MARATHON_LEVELS, SPRINT_LINES, ULTRA_SECONDS},
+    settings::{Settings, BOARD_HEIGHTS, BOARD_WIDTHS, FADE_SECONDS, MAX_HIDDEN_ROWS}
+};
+
+// Command line options, e.g. '--seed 1234', '--mode sprint --lines 40', '--mode ultra --time 180',
+// '--mode dig --lines 18 --messiness 0.5', '--mode master' or '--replay replays/game.replay'.
+// Any mode can hide the stack with '--blocks invisible' or '--blocks fading --fade 3', and be played
+// on another board with '--width 4 --height 24 --hidden 2' or with other pieces, '--pieces pieces/pentominoes.pieces'
+#[derive(Default)]
+pub struct Options {
+    pub seed: Option<u64>,
+    pub settings: Settings,
+    // Path of a piece set to play with instead of the standard tetrominoes
+    pub pieces: Option<String>,
+    pub replay: Option<String>
+}
+
+impl Options {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self> {
+        let mut options = Options::default();
+        let mut mode = None;
+        let mut lines = None;
+        let mut time = None;
+        let mut messiness = None;
+        let mut hole_change = None;
+        let mut blocks = None;
+        let mut fade = None;
+
+        while let Some(arg) = args.next() {
+            let value = args.next().with_context(|| format!("Missing value for '{}'", arg))?;
+
+            match arg.as_str() {
+                "--seed" => options.seed = Some(value.parse().with_context(|| format!("Invalid seed '{}'", value))?),
+                "--mode" => mode = Some(value),
+                "--lines" => lines = Some(value.parse().with_context(|| format!("Invalid line count '{}'", value))?),
+                "--time" => time = Some(value.parse().with_context(|| format!("Invalid time '{}'", value))?),
+                "--messiness" => messiness = Some(chance(&arg, &value)?),
+                "--hole-change" => hole_change = Some(chance(&arg, &value)?),
+                "--width" => options.settings.width = board_size(&arg, &value, BOARD_WIDTHS)?,
+                "--height" => options.settings.height = board_size(&arg, &value, BOARD_HEIGHTS)?,
+                "--hidden" => options.settings.hidden_rows = board_size(&arg, &value, 0..=MAX_HIDDEN_ROWS)?,
+                "--blocks" => blocks = Some(value),
+                "--fade" => fade = Some(value.parse().with_context(|| format!("Invalid fade time '{}'", value))?),
+                "--pieces" => options.pieces = Some(value),
+                "--replay" => options.replay = Some(value),
+                _ => bail!("Unknown argument '{}'", arg)
+            }
+        }
+
+        let mode = mode.as_deref().unwrap_or("endless");
+        ensure!(lines.is_none() || matches!(mode, "sprint" | "dig"), "'--lines' only applies to '--mode sprint' and '--mode dig'");
+        ensure!(time.is_none() || mode == "ultra", "'--time' only applies to '--mode ultra'");
+        ensure!(messiness.is_none() && hole_change.is_none() || mode == "dig", "Garbage options only apply to '--mode dig'");
+
+        options.settings.mode = match mode {
+            "endless" => Mode::Marathon { levels: None },
+            "marathon" => Mode::Marathon { levels: Some(MARATHON_LEVELS) },
+            "sprint" => {
+                let lines = lines.unwrap_or(40);
+                ensure!(SPRINT_LINES.contains(&lines), "Sprint lines must be one of {:?}", SPRINT_LINES);
+                Mode::Sprint { lines }
+            },
+            "ultra" => {
+                let seconds = time.unwrap_or(ULTRA_SECONDS);
+                ensure!(seconds > 0, "Ultra time must be at least a second");
+                Mode::Ultra { seconds }
+            },
+            "master" => Mode::Master,
+            "dig" => {
+                let lines = lines.unwrap_or(DIG_LINES);
+                ensure!(lines > 0, "Dig lines must be at least one");
+                Mode::Dig { lines }
+            },
+            _ => bail!("Unknown mode '{}'", mode)
+        };
+
+        if let Some(messiness) = messiness {
+            options.settings.garbage_messiness = messiness;
+        }
+        if let Some(hole_change) = hole_change {
+            options.settings.garbage_change = hole_change;
+        }
+
+        let blocks = blocks.as_deref().unwrap_or("visible");
+        ensure!(fade.is_none() || blocks == "fading", "'--fade' only applies to '--blocks fading'");
+
+        options.settings.fade = match blocks {
+            "visible" => None,
+            "invisible" => Some(0.0),
+            "fading" => {
+                let seconds = fade.unwrap_or(FADE_SECONDS);
+                ensure!(seconds > 0.0 && seconds.is_finite(), "Fade time must be above zero");
+                Some(seconds)
+            },
+            _ => bail!("Unknown block visibility '{}'", blocks)
+        };
+
+        Ok(options)
+    }
+}
+
+// A probability between 0 and 1
+fn chance(arg: &str, value: &str) -> Result<f32> {
+    let chance: f32 = value.parse().with_context(|| format!("Invalid value '{}' for '{}'", value, arg))?;
+    ensure!((0.0..=1.0).contains(&chance), "'{}' must be between 0 and 1", arg);
+    Ok(chance)
+}
+
+fn board_size(arg: &str, value: &str, range: RangeInclusive<u32>) -> Result<u32> {
+    let size = value.parse().with_context(|| format!("Invalid value '{}' for '{}'", value, arg))?;
+    ensure!(range.contains(&size), "'{}' must be between {} and {}", arg, range.start(), range.end());
+    Ok(size)
+}
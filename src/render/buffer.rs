@@ -1,21 +1,25 @@
 use wgpu::util::DeviceExt;
 
-use crate::systems::{GameState, GRID_SIZE};
+use crate::systems::{block::{Block, BlockKind}, GameOver, GameState, TopOut, pieces::PieceId, rotation::RotationState};
+
+use super::text::{create_centred_text, create_text, text_height, text_width};
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Vertex {
     position: [f32; 3],
-    tex_coords: [f32; 2]
+    tex_coords: [f32; 2],
+    // Multiplied with the texture, alpha blended
+    color: [f32; 4]
 }
 
 
 
 const TILE_VERTS: [Vertex; 4] =  [
-    Vertex { position: [1.0, 1.0, 0.0], tex_coords: [1.0, 0.0], }, // Top right
-    Vertex { position: [-1.0, 1.0, 0.0], tex_coords: [0.0, 0.0], }, // Top left
-    Vertex { position: [-1.0, -1.0, 0.0], tex_coords: [0.0, 1.0], }, // Bottom left
-    Vertex { position: [1.0, -1.0, 0.0], tex_coords: [1.0, 1.0], }, // Bottom right
+    Vertex { position: [1.0, 1.0, 0.0], tex_coords: [1.0, 0.0], color: SOLID }, // Top right
+    Vertex { position: [-1.0, 1.0, 0.0], tex_coords: [0.0, 0.0], color: SOLID }, // Top left
+    Vertex { position: [-1.0, -1.0, 0.0], tex_coords: [0.0, 1.0], color: SOLID }, // Bottom left
+    Vertex { position: [1.0, -1.0, 0.0], tex_coords: [1.0, 1.0], color: SOLID }, // Bottom right
 ];
 
 const TILE_INDIS: [u16; 6] = [
@@ -23,6 +27,49 @@ const TILE_INDIS: [u16; 6] = [
     0, 2, 3
 ];
 
+const SOLID: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+// Labels next to the values they name
+const DIM: [f32; 4] = [1.0, 1.0, 1.0, 0.3];
+const SHADE: [f32; 4] = [0.0, 0.0, 0.0, 0.7];
+// The ghost piece is a translucent outline this many tiles thick
+const GHOST_ALPHA: f32 = 0.6;
+const OUTLINE_WIDTH: f32 = 0.12;
+
+// Seconds a line clear lights up a hidden stack for
+const FLASH_TIME: f64 = 0.3;
+
+// The centre of texel (5, 7) in the 16x16 tile texture, which is solid white, so quads sampling only it come out flat
+const SOLID_TEXEL: [f32; 2] = [5.5 / 16.0, 7.5 / 16.0];
+
+// Sizes of a font pixel in tiles
+const TITLE_PIXEL: f32 = 0.25;
+const TEXT_PIXEL: f32 = 0.15;
+const HUD_PIXEL: f32 = 0.13;
+
+// Width in tiles of the hold panel to the left of the board and the next panel to the right
+const PANEL_WIDTH: i32 = 5;
+// Height in tiles given to each piece shown in a panel
+const SLOT_HEIGHT: f32 = 2.5;
+
+// The panels need this many rows for the whole queue and the HUD, short boards leave space above
+const MIN_VIEW_HEIGHT: i32 = 20;
+// Width in tiles the results are given, wider than the board when it's narrow
+const RESULTS_WIDTH: f32 = 10.0;
+
+// How much of the first hidden row shows above the matrix, so pieces can be seen entering
+const PEEK_HEIGHT: f32 = 0.5;
+
+// Everything is laid out in tiles, with the board's bottom left tile at [PANEL_WIDTH, 0]
+pub fn view_size(state: &GameState) -> [f32; 2] {
+    [(PANEL_WIDTH * 2 + state.size()[0]) as f32, ceiling(state).max(MIN_VIEW_HEIGHT as f32)]
+}
+
+// Height in tiles the board is drawn up to, part way into the hidden rows if there are any
+fn ceiling(state: &GameState) -> f32 {
+    let hidden = (state.size()[1] - state.visible_rows()) as f32;
+    state.visible_rows() as f32 + PEEK_HEIGHT.min(hidden)
+}
+
 impl Vertex {
 
     pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
@@ -39,29 +86,66 @@ impl Vertex {
                     offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
                     shader_location: 1,
                     format: wgpu::VertexFormat::Float32x2
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x4
                 }
             ]
         }
     }
 }
 
-pub fn create_buffers(device: &wgpu::Device, state: &GameState) -> (Option<wgpu::Buffer>, Option<wgpu::Buffer>, usize) {
+pub fn create_buffers(device: &wgpu::Device, state: &GameState, alpha: f32) -> (Option<wgpu::Buffer>, Option<wgpu::Buffer>, usize) {
 
     let mut verts : Vec<Vertex> = vec![];
     let mut indis : Vec<u16> = vec![];
+    let view = view_size(state);
+    let top = view[1];
+    let ceiling = ceiling(state);
+
+    for x in 0..state.size()[0] {
+        for y in 0..ceiling.ceil() as i32 {
+            let Some(block) = state.board[x as usize][y as usize] else { continue };
 
-    for x in 0..GRID_SIZE[0] {
-        for y in 0..GRID_SIZE[1] {
-            if state.board[x as usize][y as usize] {
-                create_tile([x, y], &mut verts, &mut indis);
+            // Rows waiting out the line clear delay flash white
+            let color = if state.clearing().contains(&y) { SOLID } else { block_color(state, block.kind, block_alpha(state, &block)) };
+            if color[3] > 0.0 {
+                create_board_tile([x as f32, y as f32], ceiling, color, &mut verts, &mut indis);
             }
         }
     }
 
-    for (y, row) in state.tetrimino.iter().enumerate() {
-        for (x, val) in row.iter().enumerate() {
-            if *val { create_tile([x as i32 + state.pos[0], y as i32+ state.pos[1]], &mut verts, &mut indis); }
-        }
+    if let Some(ghost_pos) = state.ghost_pos() {
+        let color = block_color(state, BlockKind::Piece(state.kind), GHOST_ALPHA);
+        create_outline(state, ghost_pos, color, &mut verts, &mut indis);
+    }
+
+    if state.piece_active() {
+        let color = block_color(state, BlockKind::Piece(state.kind), 1.0);
+        create_piece(state, state.interpolated_pos(alpha), color, &mut verts, &mut indis);
+    }
+
+    if let Some(kind) = state.held {
+        create_preview(state, kind, [0.0, top - SLOT_HEIGHT], &mut verts, &mut indis);
+    }
+
+    for (i, kind) in state.next_pieces().iter().enumerate() {
+        let slot = [(PANEL_WIDTH + state.size()[0]) as f32, top - SLOT_HEIGHT * (i + 1) as f32];
+        create_preview(state, *kind, slot, &mut verts, &mut indis);
+    }
+
+    create_hud(state, top, &mut verts, &mut indis);
+
+    if let Some(game_over) = state.game_over() {
+        create_results(state, game_over, top, &mut verts, &mut indis);
+    }
+
+    // Tiles to clip space, the view filling the window
+    for vert in &mut verts {
+        vert.position[0] = vert.position[0] * 2.0 / view[0] - 1.0;
+        vert.position[1] = vert.position[1] * 2.0 / view[1] - 1.0;
     }
 
     let vertex_buffer = device.create_buffer_init(
@@ -83,15 +167,181 @@ pub fn create_buffers(device: &wgpu::Device, state: &GameState) -> (Option<wgpu:
     (Some(vertex_buffer), Some(index_buffer), indis.len())
 }
 
-fn create_tile(pos: [i32; 2], verts: &mut Vec<Vertex>, indis: &mut Vec<u16>) {
+// Pieces take their colour from the set, multiplied with the white tile texture
+fn block_color(state: &GameState, kind: BlockKind, alpha: f32) -> [f32; 4] {
+    let [r, g, b] = match kind {
+        BlockKind::Piece(id) => state.pieces().piece(id).color,
+        BlockKind::Garbage => [0.55, 0.55, 0.55]
+    };
+    [r, g, b, alpha]
+}
+
+// How visible a locked block is under 'Settings::fade'. Line clears briefly light the stack
+// back up, and the whole stack is revealed once the game is over
+fn block_alpha(state: &GameState, block: &Block) -> f32 {
+    let Some(fade) = state.settings().fade else { return 1.0 };
+    if state.game_over().is_some() { return 1.0 }
+
+    let age = (state.time() - block.locked_at) as f32;
+    let faded = if fade > 0.0 { 1.0 - age / fade } else { 0.0 };
+    let flash = state.last_clear().map_or(0.0, |time| 1.0 - (state.time() - time) / FLASH_TIME) as f32;
+
+    faded.max(flash).clamp(0.0, 1.0)
+}
+
+fn create_piece(state: &GameState, pos: [f32; 2], color: [f32; 4], verts: &mut Vec<Vertex>, indis: &mut Vec<u16>) {
+    let ceiling = ceiling(state);
+
+    for (y, row) in state.tetrimino.iter().enumerate() {
+        for (x, val) in row.iter().enumerate() {
+            if *val { create_board_tile([x as f32 + pos[0], y as f32 + pos[1]], ceiling, color, verts, indis); }
+        }
+    }
+}
+
+// Traces the active piece's outer edges at 'pos'. Edges are drawn inside the cells they border and
+// never overlap, so corners aren't blended twice
+fn create_outline(state: &GameState, pos: [i32; 2], color: [f32; 4], verts: &mut Vec<Vertex>, indis: &mut Vec<u16>) {
+    let ceiling = ceiling(state);
+    let cells = &state.tetrimino;
+    let filled = |x: i32, y: i32| {
+        x >= 0 && y >= 0 && cells.get(y as usize).and_then(|row| row.get(x as usize)).copied().unwrap_or(false)
+    };
+    let w = OUTLINE_WIDTH;
+
+    for (y, row) in cells.iter().enumerate() {
+        for (x, val) in row.iter().enumerate() {
+            if !val { continue }
+
+            let (x, y) = (x as i32, y as i32);
+            let corner = [(x + pos[0]) as f32, (y + pos[1]) as f32];
+            let (bottom, top) = (!filled(x, y - 1), !filled(x, y + 1));
+
+            // The sides stop short of any top and bottom edges, which span the whole tile
+            let mut rects = vec![];
+            if bottom { rects.push(([0.0, 0.0], [1.0, w])); }
+            if top { rects.push(([0.0, 1.0 - w], [1.0, w])); }
+
+            let side_y = if bottom { w } else { 0.0 };
+            let side_height = 1.0 - side_y - if top { w } else { 0.0 };
+            if !filled(x - 1, y) { rects.push(([0.0, side_y], [w, side_height])); }
+            if !filled(x + 1, y) { rects.push(([1.0 - w, side_y], [w, side_height])); }
+
+            // Inside corners, where the edges of two neighbours meet in this tile
+            for (dx, dy) in [(-1, -1), (1, -1), (-1, 1), (1, 1)] {
+                if filled(x + dx, y) && filled(x, y + dy) && !filled(x + dx, y + dy) {
+                    let offset = |d: i32| if d < 0 { 0.0 } else { 1.0 - w };
+                    rects.push(([offset(dx), offset(dy)], [w, w]));
+                }
+            }
+
+            for (offset, size) in rects {
+                let rect_pos = [corner[0] + offset[0], corner[1] + offset[1]];
+                let height = size[1].min(ceiling - rect_pos[1]);
+                if height > 0.0 {
+                    create_rect([rect_pos[0] + PANEL_WIDTH as f32, rect_pos[1]], [size[0], height], color, verts, indis);
+                }
+            }
+        }
+    }
+}
+
+// A tile at 'pos' on the board, cut off at 'ceiling' so hidden rows only show as far as the peek
+fn create_board_tile(pos: [f32; 2], ceiling: f32, color: [f32; 4], verts: &mut Vec<Vertex>, indis: &mut Vec<u16>) {
+    let height = (ceiling - pos[1]).min(1.0);
+    if height <= 0.0 { return }
+
+    let first = verts.len();
+    create_quad([pos[0] + PANEL_WIDTH as f32, pos[1]], [1.0, height], color, None, verts, indis);
+    // The top corners sample the texture where the cut falls, so the tile isn't squashed
+    for vert in &mut verts[first..first + 2] {
+        vert.tex_coords[1] = 1.0 - height;
+    }
+}
+
+// Draws a piece in its spawn orientation centred in a panel-wide slot, shrunk if it doesn't fit
+fn create_preview(state: &GameState, kind: PieceId, slot: [f32; 2], verts: &mut Vec<Vertex>, indis: &mut Vec<u16>) {
+    let shape = state.pieces().piece(kind).cells(RotationState::Spawn);
+    let rows : Vec<usize> = (0..shape.len()).filter(|y| shape[*y].contains(&true)).collect();
+    let color = block_color(state, BlockKind::Piece(kind), 1.0);
+
+    let scale = (SLOT_HEIGHT / (rows.len() as f32 + 0.5)).min(PANEL_WIDTH as f32 / (shape[0].len() as f32 + 0.5)).min(1.0);
+    let x_offset = slot[0] + (PANEL_WIDTH as f32 - shape[0].len() as f32 * scale) / 2.0;
+    let y_offset = slot[1] + (SLOT_HEIGHT - rows.len() as f32 * scale) / 2.0 - rows[0] as f32 * scale;
+
+    for y in rows {
+        for (x, val) in shape[y].iter().enumerate() {
+            if *val { create_quad([x as f32 * scale + x_offset, y as f32 * scale + y_offset], [scale, scale], color, None, verts, indis); }
+        }
+    }
+}
+
+// Shades the board and lists how the game went over the top of it
+fn create_results(state: &GameState, game_over: GameOver, top: f32, verts: &mut Vec<Vertex>, indis: &mut Vec<u16>) {
+    let centre = PANEL_WIDTH as f32 + state.size()[0] as f32 / 2.0;
+    let width = (state.size()[0] as f32).max(RESULTS_WIDTH);
+    create_rect([centre - width / 2.0, 0.0], [width, top], SHADE, verts, indis);
+
+    let line_height = text_height(TEXT_PIXEL);
+    let mut y = top - 2.5;
+
+    let (title, subtitle) = match game_over {
+        GameOver::TopOut(TopOut::BlockOut) => ("GAME OVER", "BLOCK OUT".to_string()),
+        GameOver::TopOut(TopOut::LockOut | TopOut::PartialLockOut) => ("GAME OVER", "LOCK OUT".to_string()),
+        GameOver::TopOut(TopOut::GarbageOut) => ("GAME OVER", "GARBAGE OUT".to_string()),
+        GameOver::Finished => ("COMPLETE", state.mode_name())
+    };
+
+    create_centred_text(title, centre, y, TITLE_PIXEL, SOLID, verts, indis);
+    y -= line_height * 2.0;
+    create_centred_text(&subtitle, centre, y, TEXT_PIXEL, SOLID, verts, indis);
+    y -= line_height * 3.0;
+
+    // Label and value share a line, the label dimmed
+    for stat in state.results() {
+        let line = format!("{} {}", stat.label, stat.value);
+        let left = centre - text_width(&line, TEXT_PIXEL) / 2.0;
+        let value_x = left + text_width(&format!("{} ", stat.label), TEXT_PIXEL) + TEXT_PIXEL;
+
+        create_text(&stat.label, [left, y], TEXT_PIXEL, DIM, verts, indis);
+        create_text(&stat.value, [value_x, y], TEXT_PIXEL, SOLID, verts, indis);
+        y -= line_height * 1.5;
+    }
+
+    create_centred_text("R TO RETRY", centre, y - line_height * 1.5, TEXT_PIXEL, SOLID, verts, indis);
+}
+
+// The mode's live stats under the hold slot, each value below its label
+fn create_hud(state: &GameState, top: f32, verts: &mut Vec<Vertex>, indis: &mut Vec<u16>) {
+    let centre = PANEL_WIDTH as f32 / 2.0;
+    let line_height = text_height(HUD_PIXEL);
+    let mut y = top - SLOT_HEIGHT - line_height * 2.0;
+
+    for stat in state.hud() {
+        create_centred_text(&stat.label, centre, y, HUD_PIXEL, DIM, verts, indis);
+        y -= line_height * 1.5;
+        create_centred_text(&stat.value, centre, y, HUD_PIXEL, SOLID, verts, indis);
+        y -= line_height * 2.5;
+    }
+}
+
+// A flat coloured rectangle, 'pos' and 'size' in tiles
+pub fn create_rect(pos: [f32; 2], size: [f32; 2], color: [f32; 4], verts: &mut Vec<Vertex>, indis: &mut Vec<u16>) {
+    create_quad(pos, size, color, Some(SOLID_TEXEL), verts, indis);
+}
+
+// Samples the whole tile texture, or just 'texel' when given. Positions stay in tiles until
+// 'create_buffers' maps the finished view to clip space
+fn create_quad(pos: [f32; 2], size: [f32; 2], color: [f32; 4], texel: Option<[f32; 2]>, verts: &mut Vec<Vertex>, indis: &mut Vec<u16>) {
     let mut tile_verts : Vec<Vertex> = TILE_VERTS.iter()
         .map(|v| Vertex {
-            position: {
-                [(v.position[0] + 1.0 - GRID_SIZE[0] as f32 + (pos[0] * 2) as f32) / GRID_SIZE[0] as f32, 
-                (v.position[1] + 1.0 - GRID_SIZE[1] as f32 + (pos[1] * 2) as f32) / GRID_SIZE[1] as f32, 
-                v.position[2]]
-            },
-            tex_coords: v.tex_coords
+            position: [
+                pos[0] + (v.position[0] + 1.0) / 2.0 * size[0],
+                pos[1] + (v.position[1] + 1.0) / 2.0 * size[1],
+                v.position[2]
+            ],
+            tex_coords: texel.unwrap_or(v.tex_coords),
+            color
         })
         .collect();
 
@@ -101,4 +351,47 @@ fn create_tile(pos: [i32; 2], verts: &mut Vec<Vertex>, indis: &mut Vec<u16>) {
 
     verts.append(&mut tile_verts);
     indis.append(&mut tile_indis);
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use image::GenericImageView;
+
+    use crate::systems::{settings::Settings, GameState};
+
+    use super::{create_outline, OUTLINE_WIDTH, SOLID, SOLID_TEXEL};
+
+    #[test]
+    fn solid_texel_is_opaque_white() {
+        let img = image::load_from_memory(include_bytes!("../img/tile_cir.png")).unwrap();
+        let (width, height) = img.dimensions();
+        let x = (SOLID_TEXEL[0] * width as f32) as u32;
+        let y = (SOLID_TEXEL[1] * height as f32) as u32;
+
+        assert_eq!(img.get_pixel(x, y).0, [255, 255, 255, 255]);
+    }
+
+    // Edges that never overlap cover the perimeter times their width, less a square for each
+    // outside corner beyond the inside ones. Every outline has four more of the former
+    #[test]
+    fn outlines_trace_the_perimeter_once() {
+        for seed in 0..20 {
+            let state = GameState::new(Settings::default(), seed);
+            let cells = &state.tetrimino;
+            let filled = |x: i32, y: i32| x >= 0 && y >= 0 && cells.get(y as usize).and_then(|row| row.get(x as usize)) == Some(&true);
+            let perimeter = (0..4).flat_map(|y| (0..4).map(move |x| (x, y)))
+                .filter(|&(x, y)| filled(x, y))
+                .map(|(x, y)| [(1, 0), (-1, 0), (0, 1), (0, -1)].iter().filter(|(dx, dy)| !filled(x + dx, y + dy)).count())
+                .sum::<usize>() as f32;
+
+            let (mut verts, mut indis) = (vec![], vec![]);
+            create_outline(&state, [0, 0], SOLID, &mut verts, &mut indis);
+            let area: f32 = verts.chunks(4).map(|quad| {
+                (quad[0].position[0] - quad[1].position[0]) * (quad[0].position[1] - quad[2].position[1])
+            }).sum();
+
+            let w = OUTLINE_WIDTH;
+            assert!((area - (perimeter * w - 4.0 * w * w)).abs() < 1e-4, "seed {}", seed);
+        }
+    }
+}
@@ -2,11 +2,17 @@ use winit::window::Window;
 
 mod texture;
 mod buffer;
+mod text;
 
 use texture::Texture;
 
 use crate::systems::GameState;
 
+// Size of a single tile on screen, the window is sized to fit the whole view
+const TILE_PIXELS: u32 = 40;
+// Largest the window gets before tiles shrink
+const MAX_WINDOW: [u32; 2] = [1600, 960];
+
 pub struct State {
     surface: wgpu::Surface,
     device: wgpu::Device,
@@ -64,8 +70,7 @@ impl State {
         // Surface texture format is SRGB
         let surface_format = surface_capabilities.formats.iter()
             .copied()
-            .filter(|f| f.is_srgb())
-            .next()
+            .find(|f| f.is_srgb())
             .unwrap_or(surface_capabilities.formats[0]);
 
         let config = wgpu::SurfaceConfiguration {
@@ -151,7 +156,7 @@ impl State {
                     entry_point: "fs_main",
                     targets: &[Some(wgpu::ColorTargetState {
                         format: config.format,
-                        blend: Some(wgpu::BlendState::REPLACE),
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
                         write_mask: wgpu::ColorWrites::ALL
                     })]
                 }),
@@ -206,8 +211,9 @@ impl State {
         }
     }
 
-    pub fn update(&mut self, state: &GameState) {
-        (self.vertex_buffer, self.index_buffer, self.index_count) = buffer::create_buffers(&self.device, &state);
+    // 'alpha' is how far between simulation steps this frame falls
+    pub fn update(&mut self, state: &GameState, alpha: f32) {
+        (self.vertex_buffer, self.index_buffer, self.index_count) = buffer::create_buffers(&self.device, state, alpha);
     }
 
     pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
@@ -262,4 +268,12 @@ impl State {
 
 pub async fn new(window: Window) -> State {
     State::new(window).await
+}
+
+// Big boards get smaller tiles so the window still fits on screen
+pub fn window_size(state: &GameState) -> winit::dpi::PhysicalSize<u32> {
+    let view = buffer::view_size(state);
+    let tile = (TILE_PIXELS as f32).min(MAX_WINDOW[0] as f32 / view[0]).min(MAX_WINDOW[1] as f32 / view[1]).floor().max(1.0);
+
+    winit::dpi::PhysicalSize::new((view[0] * tile).round() as u32, (view[1] * tile).round() as u32)
 }
\ No newline at end of file
@@ -0,0 +1,86 @@
+use super::buffer::{create_rect, Vertex};
+
+// A 3x5 pixel font drawn as flat quads, enough for labels and numbers
+
+const GLYPH_SIZE: [usize; 2] = [3, 5];
+
+// Rows top to bottom, the high bit of each row is the leftmost pixel
+fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b011, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        _ => [0; 5]
+    }
+}
+
+// Width in tiles of 'text' drawn with pixels 'pixel' tiles wide
+pub fn text_width(text: &str, pixel: f32) -> f32 {
+    let chars = text.chars().count();
+    if chars == 0 { return 0.0 }
+
+    (chars * (GLYPH_SIZE[0] + 1) - 1) as f32 * pixel
+}
+
+pub fn text_height(pixel: f32) -> f32 {
+    GLYPH_SIZE[1] as f32 * pixel
+}
+
+// 'pos' is the bottom left corner of the first character, in tiles
+pub fn create_text(text: &str, pos: [f32; 2], pixel: f32, color: [f32; 4], verts: &mut Vec<Vertex>, indis: &mut Vec<u16>) {
+    for (i, c) in text.chars().enumerate() {
+        let x_offset = pos[0] + (i * (GLYPH_SIZE[0] + 1)) as f32 * pixel;
+
+        for (row, bits) in glyph(c).iter().enumerate() {
+            let y = pos[1] + (GLYPH_SIZE[1] - 1 - row) as f32 * pixel;
+
+            for x in 0..GLYPH_SIZE[0] {
+                if bits & (1 << (GLYPH_SIZE[0] - 1 - x)) != 0 {
+                    create_rect([x_offset + x as f32 * pixel, y], [pixel, pixel], color, verts, indis);
+                }
+            }
+        }
+    }
+}
+
+// Draws 'text' horizontally centred on 'centre_x'
+pub fn create_centred_text(text: &str, centre_x: f32, y: f32, pixel: f32, color: [f32; 4], verts: &mut Vec<Vertex>, indis: &mut Vec<u16>) {
+    create_text(text, [centre_x - text_width(text, pixel) / 2.0, y], pixel, color, verts, indis);
+}
@@ -2,6 +2,8 @@ use image::GenericImageView;
 use anyhow::*;
 
 pub struct Texture {
+    // Kept so the texture outlives the view and sampler that reference it
+    #[allow(dead_code)]
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
     pub sampler: wgpu::Sampler
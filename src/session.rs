@@ -0,0 +1,256 @@
+use std::collections::HashSet;
+
+use winit::event::*;
+
+use crate::systems::{
+    input::{Action, InputFrame},
+    pieces::PieceSet,
+    replay::{Playback, Replay},
+    settings::Settings,
+    GameState, FRAME_TIME
+};
+
+// Longest gap between redraws the simulation will catch up on, so a stall doesn't snowball
+const MAX_FRAME_DELAY: f32 = 0.25;
+
+// How far the arrow keys seek while watching a replay
+const SEEK_FRAMES: u32 = 5 * 60;
+const MIN_SPEED: f32 = 0.25;
+const MAX_SPEED: f32 = 8.0;
+
+#[cfg(not(target_arch = "wasm32"))]
+const REPLAY_DIR: &str = "replays";
+
+// Maps keyboard events onto engine actions. The engine only cares about presses
+// and releases, so OS key repeats are dropped here.
+#[derive(Default)]
+pub struct Controls {
+    held: HashSet<VirtualKeyCode>,
+    frame: InputFrame
+}
+
+impl Controls {
+    fn action(key: VirtualKeyCode) -> Option<Action> {
+        match key {
+            VirtualKeyCode::Left => Some(Action::MoveLeft),
+            VirtualKeyCode::Right => Some(Action::MoveRight),
+            VirtualKeyCode::Down => Some(Action::SoftDrop),
+            VirtualKeyCode::Space => Some(Action::HardDrop),
+            VirtualKeyCode::S => Some(Action::SonicDrop),
+            VirtualKeyCode::Up | VirtualKeyCode::X => Some(Action::RotateClockwise),
+            VirtualKeyCode::Z => Some(Action::RotateCounterClockwise),
+            VirtualKeyCode::A => Some(Action::Rotate180),
+            VirtualKeyCode::C | VirtualKeyCode::LShift => Some(Action::Hold),
+            _ => None
+        }
+    }
+
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        let Some((state, key)) = key_event(event) else { return false };
+        let Some(action) = Controls::action(key) else { return false };
+
+        match state {
+            ElementState::Pressed => if self.held.insert(key) {
+                self.frame.press(action);
+            },
+            ElementState::Released => if self.held.remove(&key) {
+                self.frame.release(action);
+            }
+        }
+        true
+    }
+
+    pub fn take(&mut self) -> InputFrame {
+        std::mem::take(&mut self.frame)
+    }
+}
+
+fn key_event(event: &WindowEvent) -> Option<(ElementState, VirtualKeyCode)> {
+    match event {
+        WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } => Some((*state, *key)),
+        _ => None
+    }
+}
+
+// Either a game being played and recorded, or a replay being watched
+pub enum Session {
+    Play {
+        state: GameState,
+        controls: Controls,
+        replay: Replay,
+        accumulator: f32,
+        // Every game uses this seed when set, otherwise each gets a fresh one
+        seed: Option<u64>
+    },
+    Watch(Playback)
+}
+
+impl Session {
+    pub fn play(settings: Settings, seed: Option<u64>) -> Self {
+        let game_seed = seed.unwrap_or_else(rand::random);
+
+        Session::Play {
+            state: GameState::new(settings.clone(), game_seed),
+            controls: Controls::default(),
+            replay: Replay::new(game_seed, settings),
+            accumulator: 0.0,
+            seed
+        }
+    }
+
+    pub fn watch(replay: Replay) -> Self {
+        Session::Watch(Playback::new(replay))
+    }
+
+    // The seed is shown so a sequence can be shared and raced
+    pub fn title(&self) -> String {
+        match self {
+            Session::Play { state, .. } => format!("Tetris - {} - Seed {}", state.mode_name(), state.seed()),
+            Session::Watch(playback) => {
+                let state = playback.state();
+                format!("Tetris - Replay of {} - Seed {}", state.mode_name(), state.seed())
+            }
+        }
+    }
+
+    pub fn state(&self) -> &GameState {
+        match self {
+            Session::Play { state, .. } => state,
+            Session::Watch(playback) => playback.state()
+        }
+    }
+
+    // How far the session is between steps, for interpolation
+    pub fn alpha(&self) -> f32 {
+        match self {
+            Session::Play { accumulator, .. } => accumulator / FRAME_TIME,
+            Session::Watch(playback) => playback.alpha()
+        }
+    }
+
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        match self {
+            Session::Play { controls, .. } => {
+                if key_event(event) == Some((ElementState::Pressed, VirtualKeyCode::R)) {
+                    self.restart();
+                    return true
+                }
+                controls.input(event)
+            },
+            Session::Watch(playback) => {
+                let Some((ElementState::Pressed, key)) = key_event(event) else { return false };
+
+                match key {
+                    VirtualKeyCode::R => playback.seek(0),
+                    VirtualKeyCode::Space => playback.paused = !playback.paused,
+                    VirtualKeyCode::Left => playback.seek(playback.frame().saturating_sub(SEEK_FRAMES)),
+                    VirtualKeyCode::Right => playback.seek(playback.frame() + SEEK_FRAMES),
+                    VirtualKeyCode::Up => playback.speed = (playback.speed * 2.0).min(MAX_SPEED),
+                    VirtualKeyCode::Down => playback.speed = (playback.speed / 2.0).max(MIN_SPEED),
+                    _ => return false
+                }
+                true
+            }
+        }
+    }
+
+    pub fn update(&mut self, elapsed_time: f32) {
+        match self {
+            Session::Play { state, controls, replay, accumulator, .. } => {
+                // Inputs go in with the first step, the rest of a catch up runs without them
+                *accumulator += elapsed_time.min(MAX_FRAME_DELAY);
+                while *accumulator >= FRAME_TIME && state.game_over().is_none() {
+                    let frame = controls.take();
+                    replay.record(&frame);
+                    state.step(FRAME_TIME, &frame);
+                    *accumulator -= FRAME_TIME;
+                }
+                // Nothing steps once the game is over, so there is nothing to interpolate
+                // towards and presses would only pile up until the restart
+                if state.game_over().is_some() {
+                    *accumulator = 0.0;
+                    controls.take();
+                }
+                for event in state.drain_events() {
+                    log::info!("{:?}", event);
+                }
+            },
+            Session::Watch(playback) => playback.update(elapsed_time.min(MAX_FRAME_DELAY))
+        }
+    }
+
+    // Saves the game so far and starts a new one with the same settings
+    fn restart(&mut self) {
+        self.finish();
+
+        if let Session::Play { replay, seed, .. } = self {
+            *self = Session::play(replay.settings.clone(), *seed);
+        }
+    }
+
+    // Saves the recording of the game being played, unless nothing was pressed
+    pub fn finish(&self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Session::Play { replay, .. } = self {
+            if replay.inputs.is_empty() { return }
+
+            match save_replay(replay) {
+                Ok(path) => println!("Replay saved to {}", path.display()),
+                Err(e) => eprintln!("Couldn't save replay: {:#}", e)
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_replay(replay: &Replay) -> anyhow::Result<std::path::PathBuf> {
+    let time = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs();
+    let path = std::path::Path::new(REPLAY_DIR).join(format!("{}-{}.replay", time, replay.seed));
+
+    std::fs::create_dir_all(REPLAY_DIR)?;
+    std::fs::write(&path, replay.to_bytes())?;
+    Ok(path)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_replay(path: &str) -> anyhow::Result<Replay> {
+    use anyhow::Context;
+
+    let bytes = std::fs::read(path).with_context(|| format!("Couldn't read replay '{}'", path))?;
+    Replay::from_bytes(&bytes).with_context(|| format!("Couldn't load replay '{}'", path))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_pieces(path: &str, width: u32) -> anyhow::Result<PieceSet> {
+    use anyhow::Context;
+
+    let source = std::fs::read_to_string(path).with_context(|| format!("Couldn't read piece set '{}'", path))?;
+    let pieces = PieceSet::parse(&source).with_context(|| format!("Couldn't load piece set '{}'", path))?;
+    pieces.check_width(width).with_context(|| format!("Can't play piece set '{}'", path))?;
+    Ok(pieces)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finished_games_stop_accumulating() {
+        let settings = Settings { width: 4, height: 4, hidden_rows: 0, start_level: 15, ..Settings::default() };
+        let mut session = Session::play(settings, Some(1));
+        for _ in 0..60 * 60 {
+            session.update(FRAME_TIME * 1.5);
+        }
+
+        assert!(session.state().game_over().is_some());
+        assert_eq!(session.alpha(), 0.0);
+    }
+}
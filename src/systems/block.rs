@@ -0,0 +1,16 @@
+use super::pieces::PieceId;
+
+// What left a block on the board
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlockKind {
+    Piece(PieceId),
+    Garbage
+}
+
+// A filled cell of the board, left behind by a locked piece or rising garbage
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Block {
+    pub kind: BlockKind,
+    // Game time in seconds the block landed, so it can fade from view under 'Settings::fade'
+    pub locked_at: f64
+}
@@ -0,0 +1,14 @@
+use super::{scoring::ClearResult, spin::Spin, pieces::PieceId, GameOver};
+
+// Things worth reacting to outside the rules, collected until 'GameState::drain_events'
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameEvent {
+    Spin {
+        kind: PieceId,
+        spin: Spin,
+        lines: usize
+    },
+    // Sent for every piece that locks, whether or not it cleared anything
+    Lock(ClearResult),
+    GameOver(GameOver)
+}
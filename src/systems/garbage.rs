@@ -0,0 +1,88 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+// Deals garbage: full rows with a single hole. Rows asked for together form a batch,
+// the way one attack's lines arrive together in versus.
+pub struct GarbageGenerator {
+    rng: ChaCha8Rng,
+    width: usize,
+    // Chance the hole moves between rows of the same batch
+    messiness: f32,
+    // Chance the hole moves at the start of a new batch
+    change: f32,
+    hole: usize
+}
+
+impl GarbageGenerator {
+    pub fn new(seed: u64, width: usize, messiness: f32, change: f32) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let hole = rng.gen_range(0..width);
+
+        GarbageGenerator {
+            rng,
+            width,
+            messiness: messiness.clamp(0.0, 1.0),
+            change: change.clamp(0.0, 1.0),
+            hole
+        }
+    }
+
+    // 'count' rows, bottom row first, each 'true' except for its hole
+    pub fn batch(&mut self, count: usize) -> Vec<Vec<bool>> {
+        (0..count)
+            .map(|i| {
+                let chance = if i == 0 { self.change } else { self.messiness };
+                if self.rng.gen_bool(chance as f64) {
+                    self.move_hole();
+                }
+                (0..self.width).map(|x| x != self.hole).collect()
+            })
+            .collect()
+    }
+
+    // Moves the hole to any other column
+    fn move_hole(&mut self) {
+        if self.width < 2 { return }
+
+        self.hole = (self.hole + self.rng.gen_range(1..self.width)) % self.width;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn holes(rows: &[Vec<bool>]) -> Vec<usize> {
+        rows.iter().map(|row| {
+            assert_eq!(row.iter().filter(|cell| !**cell).count(), 1);
+            row.iter().position(|cell| !cell).unwrap()
+        }).collect()
+    }
+
+    #[test]
+    fn clean_garbage_keeps_its_hole() {
+        let mut garbage = GarbageGenerator::new(1, 10, 0.0, 0.0);
+        let first = holes(&garbage.batch(5));
+        let second = holes(&garbage.batch(5));
+
+        assert!(first.iter().chain(&second).all(|hole| *hole == first[0]));
+    }
+
+    #[test]
+    fn messy_garbage_moves_its_hole_every_row() {
+        let mut garbage = GarbageGenerator::new(1, 10, 1.0, 1.0);
+        let holes = holes(&garbage.batch(20));
+
+        assert!(holes.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn new_batches_move_the_hole_between_them() {
+        let mut garbage = GarbageGenerator::new(1, 10, 0.0, 1.0);
+        for _ in 0..10 {
+            let (first, second) = (holes(&garbage.batch(3)), holes(&garbage.batch(3)));
+            assert!(first.iter().all(|hole| *hole == first[0]) && second.iter().all(|hole| *hole == second[0]));
+            assert_ne!(first[0], second[0]);
+        }
+    }
+}
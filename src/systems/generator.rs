@@ -0,0 +1,201 @@
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use super::pieces::{PieceId, PieceSet};
+
+// Every generator draws from ChaCha8, a fixed algorithm, so a seed deals the same pieces
+// whatever the platform or rand version and shared seeds and replays stay valid
+pub trait PieceGenerator {
+    fn next(&mut self) -> PieceId;
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Randomizer {
+    #[default]
+    Bag7,
+    Bag14,
+    Classic,
+    History,
+    Random
+}
+
+impl Randomizer {
+    pub const ALL: [Randomizer; 5] = [
+        Randomizer::Bag7,
+        Randomizer::Bag14,
+        Randomizer::Classic,
+        Randomizer::History,
+        Randomizer::Random
+    ];
+
+    // Bag7 and Bag14 deal one or two of every piece in the set per bag, whatever its size
+    pub fn generator(self, seed: u64, pieces: &PieceSet) -> Box<dyn PieceGenerator> {
+        match self {
+            Randomizer::Bag7 => Box::new(BagGenerator::new(seed, pieces.len(), 1)),
+            Randomizer::Bag14 => Box::new(BagGenerator::new(seed, pieces.len(), 2)),
+            Randomizer::Classic => Box::new(ClassicGenerator::new(seed, pieces.len())),
+            Randomizer::History => Box::new(HistoryGenerator::new(seed, pieces)),
+            Randomizer::Random => Box::new(RandomGenerator::new(seed, pieces.len()))
+        }
+    }
+}
+
+// Deals every piece 'copies' times in a shuffled order before refilling
+pub struct BagGenerator {
+    rng: ChaCha8Rng,
+    count: usize,
+    copies: usize,
+    bag: Vec<PieceId>
+}
+
+impl BagGenerator {
+    pub fn new(seed: u64, count: usize, copies: usize) -> Self {
+        BagGenerator {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            count,
+            copies,
+            bag: vec![]
+        }
+    }
+}
+
+impl PieceGenerator for BagGenerator {
+    fn next(&mut self) -> PieceId {
+        if self.bag.is_empty() {
+            for _ in 0..self.copies {
+                self.bag.extend((0..self.count).map(PieceId));
+            }
+            self.bag.shuffle(&mut self.rng);
+        }
+
+        self.bag.pop().unwrap()
+    }
+}
+
+// NES style: roll an eighth "reroll" slot, and reroll once on a repeat
+pub struct ClassicGenerator {
+    rng: ChaCha8Rng,
+    count: usize,
+    previous: Option<PieceId>
+}
+
+impl ClassicGenerator {
+    pub fn new(seed: u64, count: usize) -> Self {
+        ClassicGenerator {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            count,
+            previous: None
+        }
+    }
+}
+
+impl PieceGenerator for ClassicGenerator {
+    fn next(&mut self) -> PieceId {
+        let roll = self.rng.gen_range(0..self.count + 1);
+
+        let kind = if roll < self.count && Some(PieceId(roll)) != self.previous {
+            PieceId(roll)
+        } else {
+            PieceId(self.rng.gen_range(0..self.count))
+        };
+
+        self.previous = Some(kind);
+        kind
+    }
+}
+
+const HISTORY_ROLLS: usize = 6;
+
+// TGM style: reroll up to 'HISTORY_ROLLS' times while the piece is one of the last four dealt.
+// The S, Z and O rules only apply to sets with pieces of those names
+pub struct HistoryGenerator {
+    rng: ChaCha8Rng,
+    count: usize,
+    history: [Option<PieceId>; 4],
+    // Pieces the first deal avoids, those that would force an overhang
+    overhangs: Vec<PieceId>,
+    first: bool
+}
+
+impl HistoryGenerator {
+    pub fn new(seed: u64, pieces: &PieceSet) -> Self {
+        let (s, z) = (pieces.find("S"), pieces.find("Z"));
+
+        HistoryGenerator {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            count: pieces.len(),
+            history: [z, z, s, s],
+            overhangs: ["S", "Z", "O"].iter().filter_map(|name| pieces.find(name)).collect(),
+            first: true
+        }
+    }
+}
+
+impl PieceGenerator for HistoryGenerator {
+    fn next(&mut self) -> PieceId {
+        let mut kind = PieceId(self.rng.gen_range(0..self.count));
+
+        if self.first {
+            // A set made only of those pieces has to start with one
+            while self.overhangs.contains(&kind) && self.overhangs.len() < self.count {
+                kind = PieceId(self.rng.gen_range(0..self.count));
+            }
+            self.first = false;
+        } else {
+            for _ in 1..HISTORY_ROLLS {
+                if !self.history.contains(&Some(kind)) { break }
+                kind = PieceId(self.rng.gen_range(0..self.count));
+            }
+        }
+
+        self.history.rotate_left(1);
+        self.history[3] = Some(kind);
+        kind
+    }
+}
+
+pub struct RandomGenerator {
+    rng: ChaCha8Rng,
+    count: usize
+}
+
+impl RandomGenerator {
+    pub fn new(seed: u64, count: usize) -> Self {
+        RandomGenerator {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            count
+        }
+    }
+}
+
+impl PieceGenerator for RandomGenerator {
+    fn next(&mut self) -> PieceId {
+        PieceId(self.rng.gen_range(0..self.count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeds_deal_fixed_pieces() {
+        let mut generator = Randomizer::Bag7.generator(42, &PieceSet::standard());
+        let dealt: Vec<usize> = (0..14).map(|_| generator.next().0).collect();
+
+        assert_eq!(dealt, vec![1, 4, 2, 6, 5, 3, 0, 1, 4, 5, 2, 6, 3, 0]);
+    }
+
+    #[test]
+    fn bags_deal_every_piece_once() {
+        let pieces = PieceSet::standard();
+        for seed in 0..20 {
+            let mut generator = Randomizer::Bag7.generator(seed, &pieces);
+            for _ in 0..10 {
+                let mut bag: Vec<usize> = (0..pieces.len()).map(|_| generator.next().0).collect();
+                bag.sort();
+                assert_eq!(bag, (0..pieces.len()).collect::<Vec<_>>());
+            }
+        }
+    }
+}
@@ -0,0 +1,54 @@
+// Abstract controls, so the rules can be driven by a keyboard, a bot or a replay alike
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    SonicDrop,
+    RotateClockwise,
+    RotateCounterClockwise,
+    Rotate180,
+    Hold
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::SoftDrop,
+        Action::HardDrop,
+        Action::SonicDrop,
+        Action::RotateClockwise,
+        Action::RotateCounterClockwise,
+        Action::Rotate180,
+        Action::Hold
+    ];
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Input {
+    Press(Action),
+    Release(Action)
+}
+
+// Everything that happened to the controls since the last step, in order
+#[derive(Clone, Default, Debug)]
+pub struct InputFrame {
+    pub inputs: Vec<Input>
+}
+
+impl InputFrame {
+    pub fn press(&mut self, action: Action) {
+        self.inputs.push(Input::Press(action));
+    }
+
+    pub fn release(&mut self, action: Action) {
+        self.inputs.push(Input::Release(action));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+}
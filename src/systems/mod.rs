@@ -0,0 +1,1051 @@
+pub mod block;
+pub mod event;
+pub mod garbage;
+pub mod generator;
+pub mod input;
+pub mod mode;
+pub mod pieces;
+pub mod replay;
+pub mod rotation;
+pub mod scoring;
+pub mod settings;
+pub mod shift;
+pub mod spin;
+
+use block::{Block, BlockKind};
+use event::GameEvent;
+use garbage::GarbageGenerator;
+use generator::PieceGenerator;
+use input::{Action, Input, InputFrame};
+use mode::{GameMode, Stat};
+use pieces::{Piece, PieceId, PieceSet};
+use rotation::{Kick, Rotation, RotationState};
+use scoring::Scoring;
+use settings::{HoldMode, LockReset, Settings, Timings, MAX_PREVIEW, MAX_LOCK_RESETS};
+use shift::AutoShift;
+use spin::Spin;
+
+// Guideline timings are given in 60 Hz frames, so the game is stepped at that rate
+pub const FRAME_TIME: f32 = 1.0 / 60.0;
+
+// Indexed [x][y], columns left to right and rows from the floor up
+pub type Board = Vec<Vec<Option<Block>>>;
+
+pub struct GameState {
+    pub board: Board,
+    // Columns and rows of the board, hidden rows included
+    size: [i32; 2],
+    // Rows shown in the matrix, the skyline a piece can't lock above. The rest are hidden above it
+    visible_rows: i32,
+    pub pos: [i32; 2],
+    // Where the active piece was at the start of the last step
+    last_pos: [i32; 2],
+    pub tetrimino: Vec<Vec<bool>>,
+    pub kind: PieceId,
+    pub rotation: RotationState,
+    pub held: Option<PieceId>,
+    pub can_hold: bool,
+    settings: Settings,
+    seed: u64,
+    generator: Box<dyn PieceGenerator>,
+    queue: Vec<PieceId>,
+    shift: AutoShift,
+    soft_drop: bool,
+    // Seconds left before the next piece appears
+    entry_timer: f32,
+    // Full rows waiting out the line clear delay, highest first
+    clearing: Vec<i32>,
+    clear_timer: f32,
+    // Game time of the last line clear
+    last_clear: Option<f64>,
+    tick: f32,
+    lock_timer: f32,
+    lock_resets: u32,
+    lowest_row: i32,
+    // The kick used if the last thing the active piece did was rotate
+    last_kick: Option<Kick>,
+    events: Vec<GameEvent>,
+    // Seconds played, kept in f64 so long games don't drift
+    time: f64,
+    mode: Box<dyn GameMode>,
+    garbage: GarbageGenerator,
+    // Height of the garbage at the bottom of the board, it only ever sits below the player's blocks
+    garbage_rows: usize,
+    game_over: Option<GameOver>,
+    pub scoring: Scoring
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Dir {
+    Right,
+    Left,
+}
+
+// Guideline ways for a game to end
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TopOut {
+    // The next piece spawned overlapping the stack
+    BlockOut,
+    // A piece locked entirely above the skyline
+    LockOut,
+    // A piece locked partly above the skyline, only under 'Settings::partial_lock_out'
+    PartialLockOut,
+    // Rising garbage pushed blocks out of the top of the board
+    GarbageOut
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameOver {
+    TopOut(TopOut),
+    // The mode's goal was reached
+    Finished
+}
+
+impl Default for GameState {
+    fn default() -> Self {
+        Self::new(Settings::default(), rand::random())
+    }
+}
+
+impl GameState {
+    // The same seed and settings always deal the same pieces
+    pub fn new(settings: Settings, seed: u64) -> Self {
+        let mut generator = settings.randomizer.generator(seed, &settings.pieces);
+        let kind = generator.next();
+        let queue = (0..settings.preview.min(MAX_PREVIEW)).map(|_| generator.next()).collect();
+        let size = [settings.width as i32, (settings.height + settings.hidden_rows) as i32];
+        let visible_rows = settings.height as i32;
+        // Garbage draws from its own stream so it never changes the pieces dealt
+        let garbage = GarbageGenerator::new(!seed, size[0] as usize, settings.garbage_messiness, settings.garbage_change);
+
+        let mut state = GameState {
+            board: vec![vec![None; size[1] as usize]; size[0] as usize],
+            size,
+            visible_rows,
+            shift: AutoShift::default(),
+            soft_drop: false,
+            entry_timer: 0.0,
+            clearing: vec![],
+            clear_timer: 0.0,
+            last_clear: None,
+            tick: 0.0,
+            lock_timer: 0.0,
+            lock_resets: 0,
+            lowest_row: 0,
+            last_pos: [0, 0],
+            last_kick: None,
+            events: vec![],
+            time: 0.0,
+            mode: settings.mode.game_mode(),
+            garbage,
+            garbage_rows: 0,
+            game_over: None,
+            pos: [0, 0],
+            scoring: Scoring::new(settings.start_level),
+            tetrimino: vec![],
+            kind,
+            rotation: RotationState::Spawn,
+            held: None,
+            can_hold: true,
+            settings,
+            seed,
+            generator,
+            queue
+        };
+
+        // The first piece spawns once any starting garbage is in
+        state.refill_garbage();
+        state.spawn_tetrimino(kind);
+        state
+    }
+
+    // Applies the frame's inputs in order, then advances the game by 'elapsed_time' seconds
+    pub fn step(&mut self, elapsed_time: f32, frame: &InputFrame) {
+        if self.game_over.is_some() { return }
+
+        self.time += elapsed_time as f64;
+        if let Some(game_over) = self.mode.tick(&self.scoring, self.time) {
+            self.end(game_over);
+            return
+        }
+
+        self.last_pos = self.pos;
+
+        for input in &frame.inputs {
+            self.input(*input);
+        }
+        // A drop during the inputs may have ended the game
+        if self.game_over.is_some() { return }
+
+        if self.spawning() {
+            self.shift.charge(elapsed_time);
+
+            if !self.clearing.is_empty() {
+                self.clear_timer -= elapsed_time;
+                if self.clear_timer <= 0.0 {
+                    self.finish_clear();
+                }
+                return
+            }
+
+            self.entry_timer -= elapsed_time;
+            if self.entry_timer <= 0.0 {
+                self.spawn_next();
+            }
+            return
+        }
+
+        let timings = self.timings();
+
+        let shift = match self.shift.dir() {
+            Some(Dir::Left) => -1,
+            Some(Dir::Right) => 1,
+            None => 0
+        };
+
+        for _ in 0..self.shift.update(elapsed_time, timings.das, self.settings.arr) {
+            if !self.move_tetrimino([shift, 0]) { break }
+        }
+
+        let mut gravity = if self.soft_drop { self.settings.soft_drop } else { 1.0 };
+
+        // Infinite soft drop lands the piece straight away and then falls at the normal rate
+        if gravity.is_infinite() {
+            let distance = self.sonic_drop();
+            self.scoring.soft_drop(distance);
+            gravity = 1.0;
+        }
+
+        self.tick += elapsed_time * gravity;
+
+        let row_time = timings.gravity;
+        if timings.instant_gravity() {
+            // Stepping row by row could take several frames to cross a tall board
+            self.sonic_drop();
+            self.tick = 0.0;
+        }
+        while self.tick > row_time {
+            self.tick -= row_time;
+            if !self.move_tetrimino([0, -1]) {
+                self.tick = 0.0;
+                break
+            }
+            if self.soft_drop {
+                self.scoring.soft_drop(1);
+            }
+        }
+
+        if self.grounded() {
+            self.lock_timer += elapsed_time;
+
+            let out_of_resets = timings.lock_reset == LockReset::Move && self.lock_resets >= MAX_LOCK_RESETS;
+            if out_of_resets || self.lock_timer >= timings.lock_delay {
+                self.lock_tetrimino();
+            }
+        }
+    }
+
+    fn move_tetrimino(&mut self, dir: [i32; 2]) -> bool {
+        let new_pos = [self.pos[0] + dir[0], self.pos[1] + dir[1]];
+
+        if !self.fits(&self.tetrimino, new_pos) { return false }
+
+        self.pos = new_pos;
+        self.last_kick = None;
+        self.reset_lock();
+        true
+    }
+
+    fn grounded(&self) -> bool {
+        !self.fits(&self.tetrimino, [self.pos[0], self.pos[1] - 1])
+    }
+
+    // Called after the active piece moves or rotates. Reaching a new lowest row always
+    // restarts the lock timer, other movement only does so under 'LockReset::Move'
+    fn reset_lock(&mut self) {
+        let lock_reset = self.timings().lock_reset;
+
+        if self.pos[1] < self.lowest_row {
+            self.lowest_row = self.pos[1];
+            if lock_reset != LockReset::Never {
+                self.lock_timer = 0.0;
+                self.lock_resets = 0;
+            }
+        } else if lock_reset == LockReset::Move && self.lock_timer > 0.0 && self.lock_resets < MAX_LOCK_RESETS {
+            self.lock_timer = 0.0;
+            self.lock_resets += 1;
+        }
+    }
+
+    // Number of rows the active piece can fall before it lands
+    pub fn drop_distance(&self) -> i32 {
+        let mut distance = 0;
+        while self.fits(&self.tetrimino, [self.pos[0], self.pos[1] - distance - 1]) {
+            distance += 1;
+        }
+        distance
+    }
+
+    // Position of the active piece 'alpha' of the way from the last step to the next
+    pub fn interpolated_pos(&self, alpha: f32) -> [f32; 2] {
+        [
+            self.last_pos[0] as f32 + (self.pos[0] - self.last_pos[0]) as f32 * alpha,
+            self.last_pos[1] as f32 + (self.pos[1] - self.last_pos[1]) as f32 * alpha
+        ]
+    }
+
+    // Where a hard drop would land the active piece, if the ghost is shown. A fading or invisible
+    // stack never has one, it would rest on the hidden blocks and give their outline away
+    pub fn ghost_pos(&self) -> Option<[i32; 2]> {
+        if !self.settings.ghost || self.settings.fade.is_some() || !self.mode.ghost() || !self.piece_active() { return None }
+
+        Some([self.pos[0], self.pos[1] - self.drop_distance()])
+    }
+
+    // Moves the piece straight to the floor without locking it
+    pub fn sonic_drop(&mut self) -> i32 {
+        if !self.piece_active() { return 0 }
+
+        let distance = self.drop_distance();
+        if distance > 0 {
+            self.pos[1] -= distance;
+            self.last_kick = None;
+            self.reset_lock();
+        }
+        distance
+    }
+
+    pub fn hard_drop(&mut self) -> i32 {
+        if !self.piece_active() { return 0 }
+
+        let distance = self.sonic_drop();
+        self.scoring.hard_drop(distance);
+        self.lock_tetrimino();
+        distance
+    }
+
+    fn lock_tetrimino(&mut self) {
+        let spin = self.detect_spin();
+        let top_out = self.lock_out();
+
+        let block = Block { kind: BlockKind::Piece(self.kind), locked_at: self.time };
+        for (y, row) in self.tetrimino.iter().enumerate() {
+            for (x, val) in row.iter().enumerate() {
+                if *val { self.board[(x as i32 + self.pos[0]) as usize][(y as i32 + self.pos[1]) as usize] = Some(block); }
+            }
+        }
+
+        let full_rows = self.full_rows();
+        let cleared = full_rows.len();
+        let garbage_cleared = full_rows.iter().filter(|y| (**y as usize) < self.garbage_rows).count();
+        self.garbage_rows -= garbage_cleared;
+        let perfect_clear = cleared > 0 && self.board.iter()
+            .all(|column| column.iter().enumerate().all(|(y, cell)| cell.is_none() || full_rows.contains(&(y as i32))));
+        let result = self.scoring.lock(cleared, spin, perfect_clear);
+        self.scoring.garbage_cleared += garbage_cleared as u32;
+        if cleared > 0 {
+            self.last_clear = Some(self.time);
+        }
+
+        if spin != Spin::None {
+            self.events.push(GameEvent::Spin { kind: self.kind, spin, lines: cleared });
+        }
+        self.events.push(GameEvent::Lock(result));
+
+        // Reaching the goal wins even if the piece also topped out
+        let game_over = self.mode.lock(&result, &self.scoring, self.time).or(top_out.map(GameOver::TopOut));
+        if let Some(game_over) = game_over {
+            self.collapse_rows(&full_rows);
+            self.end(game_over);
+            return
+        }
+
+        self.can_hold = true;
+        let timings = self.timings();
+        self.entry_timer = timings.entry_delay;
+        self.clearing = full_rows;
+        self.clear_timer = timings.line_clear_delay;
+
+        if self.clear_timer <= 0.0 {
+            self.finish_clear();
+        }
+    }
+
+    // Drops the stack onto the cleared rows once the line clear delay is over, then starts the entry delay
+    fn finish_clear(&mut self) {
+        let rows = std::mem::take(&mut self.clearing);
+        self.collapse_rows(&rows);
+
+        self.refill_garbage();
+        if self.game_over.is_some() { return }
+
+        if !self.spawning() {
+            self.spawn_next();
+        }
+    }
+
+    fn timings(&self) -> Timings {
+        self.mode.timings(Timings {
+            gravity: self.scoring.gravity(),
+            das: self.settings.das,
+            lock_delay: self.settings.lock_delay,
+            lock_reset: self.settings.lock_reset,
+            entry_delay: self.settings.entry_delay,
+            line_clear_delay: self.settings.line_clear_delay
+        })
+    }
+
+    // Checked against where the piece locks, before any lines clear
+    fn lock_out(&self) -> Option<TopOut> {
+        let rows: Vec<i32> = self.tetrimino.iter().enumerate()
+            .filter(|(_, row)| row.contains(&true))
+            .map(|(y, _)| y as i32 + self.pos[1])
+            .collect();
+
+        if rows.iter().all(|y| *y >= self.visible_rows) {
+            Some(TopOut::LockOut)
+        } else if self.settings.partial_lock_out && rows.iter().any(|y| *y >= self.visible_rows) {
+            Some(TopOut::PartialLockOut)
+        } else {
+            None
+        }
+    }
+
+    // Tops the garbage back up to what the mode wants on the board, while there is no piece in play
+    fn refill_garbage(&mut self) {
+        let count = self.mode.garbage(&self.scoring, self.garbage_rows);
+        if count == 0 { return }
+
+        let rows = self.garbage.batch(count);
+        self.push_garbage(&rows);
+    }
+
+    // Garbage arriving mid-piece, e.g. from an opponent. The active piece rises with the stack if it has to
+    pub fn add_garbage(&mut self, rows: &[Vec<bool>]) {
+        let count = self.push_garbage(rows) as i32;
+
+        if count == 0 || !self.piece_active() { return }
+
+        match (0..=count).find(|lift| self.fits(&self.tetrimino, [self.pos[0], self.pos[1] + lift])) {
+            Some(lift) => {
+                self.pos[1] += lift;
+                self.last_pos[1] += lift;
+                self.lowest_row += lift;
+            },
+            None => self.end(GameOver::TopOut(TopOut::GarbageOut))
+        }
+    }
+
+    // Pushes the stack up to fit 'rows' in underneath, bottom row first, and returns how many went in.
+    // Blocks pushed off the top end the game
+    fn push_garbage(&mut self, rows: &[Vec<bool>]) -> usize {
+        let height = self.size[1] as usize;
+        let rows = &rows[..rows.len().min(height)];
+        let count = rows.len();
+        if count == 0 || self.game_over.is_some() { return 0 }
+
+        let overflow = self.board.iter().any(|column| column[height - count..].iter().any(Option::is_some));
+
+        let block = Block { kind: BlockKind::Garbage, locked_at: self.time };
+        for (x, column) in self.board.iter_mut().enumerate() {
+            column.rotate_right(count);
+            for (y, row) in rows.iter().enumerate() {
+                column[y] = row.get(x).copied().unwrap_or(false).then_some(block);
+            }
+        }
+        self.garbage_rows = (self.garbage_rows + count).min(height);
+
+        if overflow {
+            self.end(GameOver::TopOut(TopOut::GarbageOut));
+        }
+        count
+    }
+
+    fn end(&mut self, game_over: GameOver) {
+        self.game_over = Some(game_over);
+        self.events.push(GameEvent::GameOver(game_over));
+    }
+
+    fn detect_spin(&self) -> Spin {
+        let Some(kick) = self.last_kick else { return Spin::None };
+
+        if self.piece().corner_spins {
+            return spin::t_spin(self.pos, &kick, |cell| !self.in_bounds(cell) || self.cell_exists(cell))
+        }
+
+        // Any other piece counts when it can't move left, right or up
+        let immobile = [[-1, 0], [1, 0], [0, 1]].iter()
+            .all(|dir| !self.fits(&self.tetrimino, [self.pos[0] + dir[0], self.pos[1] + dir[1]]));
+
+        if self.settings.all_spin && immobile { Spin::Mini } else { Spin::None }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn drain_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    // True between a piece locking and the next one appearing, through any line clear and entry delay
+    pub fn spawning(&self) -> bool {
+        self.entry_timer > 0.0 || !self.clearing.is_empty()
+    }
+
+    // How the game ended, once it has. A finished game ignores further steps
+    pub fn game_over(&self) -> Option<GameOver> {
+        self.game_over
+    }
+
+    pub fn pieces(&self) -> &PieceSet {
+        &self.settings.pieces
+    }
+
+    // The active piece's definition
+    pub fn piece(&self) -> &Piece {
+        self.settings.pieces.piece(self.kind)
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    // Columns and rows of the board, hidden rows included
+    pub fn size(&self) -> [i32; 2] {
+        self.size
+    }
+
+    pub fn visible_rows(&self) -> i32 {
+        self.visible_rows
+    }
+
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    // Full rows waiting out the line clear delay, highest first
+    pub fn clearing(&self) -> &[i32] {
+        &self.clearing
+    }
+
+    pub fn last_clear(&self) -> Option<f64> {
+        self.last_clear
+    }
+
+    pub fn mode_name(&self) -> String {
+        self.mode.name()
+    }
+
+    // What the mode shows beside the board while playing
+    pub fn hud(&self) -> Vec<Stat> {
+        self.mode.hud(&self.scoring, self.time)
+    }
+
+    // What the mode shows once the game is over
+    pub fn results(&self) -> Vec<Stat> {
+        self.mode.results(&self.scoring, self.time)
+    }
+
+    // True while there is a piece in play to move, not during entry delay or after the game ends
+    pub fn piece_active(&self) -> bool {
+        !self.spawning() && self.game_over.is_none()
+    }
+
+    fn spawn_next(&mut self) {
+        self.entry_timer = 0.0;
+        let kind = self.next_tetrimino();
+        self.spawn_tetrimino(kind);
+    }
+
+    // Deals the front of the queue and tops it back up from the generator
+    fn next_tetrimino(&mut self) -> PieceId {
+        self.queue.push(self.generator.next());
+        self.queue.remove(0)
+    }
+
+    pub fn next_pieces(&self) -> &[PieceId] {
+        &self.queue
+    }
+
+    fn spawn_tetrimino(&mut self, kind: PieceId) {
+        self.kind = kind;
+        self.tetrimino = self.piece().cells(RotationState::Spawn).to_vec();
+        self.rotation = RotationState::Spawn;
+        self.pos = GameState::spawn_pos(self.piece(), self.size, self.visible_rows);
+        self.tick = 0.0;
+        self.lock_timer = 0.0;
+        self.lock_resets = 0;
+        self.last_kick = None;
+
+        if !self.fits(&self.tetrimino, self.pos) {
+            self.end(GameOver::TopOut(TopOut::BlockOut));
+        } else if self.fits(&self.tetrimino, [self.pos[0], self.pos[1] - 1]) {
+            // Pieces drop one row into view straight away when nothing is in the way
+            self.pos[1] -= 1;
+        }
+
+        // At 20G the piece lands as it appears
+        if self.game_over.is_none() && self.timings().instant_gravity() {
+            self.pos[1] -= self.drop_distance();
+        }
+
+        self.last_pos = self.pos;
+        self.lowest_row = self.pos[1];
+    }
+
+    // Swaps the active piece with the held one, or stores it and deals the next piece
+    pub fn hold_tetrimino(&mut self) -> bool {
+        if !self.piece_active() { return false }
+
+        match self.settings.hold {
+            HoldMode::Disabled => return false,
+            HoldMode::Once if !self.can_hold => return false,
+            _ => {}
+        }
+
+        let kind = match self.held.replace(self.kind) {
+            Some(kind) => kind,
+            None => self.next_tetrimino()
+        };
+
+        self.can_hold = self.settings.hold == HoldMode::Infinite;
+        self.spawn_tetrimino(kind);
+        true
+    }
+
+    // Centres the bounding box, rounding left, and rests the piece on the skyline in the first hidden row.
+    // Boards with too few hidden rows for that spawn it as high as it fits
+    // Sets can move pieces from there with 'Piece::spawn'
+    fn spawn_pos(piece: &Piece, size: [i32; 2], visible_rows: i32) -> [i32; 2] {
+        let shape = piece.cells(RotationState::Spawn);
+        let bottom = shape.iter().position(|row| row.contains(&true)).unwrap_or(0) as i32;
+        let top = shape.iter().rposition(|row| row.contains(&true)).unwrap_or(0) as i32;
+
+        [
+            piece.spawn_x(size[0]),
+            (visible_rows - bottom).min(size[1] - 1 - top) + piece.spawn[1]
+        ]
+    }
+
+    // Tries each offset of the kick table in turn and keeps the first that fits
+    pub fn rotate_tetrimino(&mut self, rotation: Rotation) -> Option<Kick> {
+        if !self.piece_active() { return None }
+
+        let pieces = self.settings.pieces.clone();
+        let piece = pieces.piece(self.kind);
+        let to = self.rotation.rotate(rotation);
+        let rotated = piece.cells(to);
+
+        for (index, offset) in piece.kicks(self.rotation, rotation).iter().enumerate() {
+            let pos = [self.pos[0] + offset[0], self.pos[1] + offset[1]];
+            if !self.fits(rotated, pos) { continue }
+
+            let kick = Kick {
+                from: self.rotation,
+                to,
+                index,
+                offset: *offset
+            };
+
+            self.tetrimino = rotated.to_vec();
+            self.pos = pos;
+            self.rotation = kick.to;
+            self.reset_lock();
+            self.shift.cut(self.settings.das_cut);
+            self.last_kick = Some(kick);
+            return Some(kick)
+        }
+
+        None
+    }
+
+    fn fits(&self, tetrimino: &[Vec<bool>], pos: [i32; 2]) -> bool {
+        for (y, row) in tetrimino.iter().enumerate() {
+            for (x, val) in row.iter().enumerate() {
+                if !val { continue }
+
+                let cell = [x as i32 + pos[0], y as i32 + pos[1]];
+                if !self.in_bounds(cell) || self.cell_exists(cell) { return false }
+            }
+        }
+
+        true
+    }
+
+    fn in_bounds(&self, pos: [i32; 2]) -> bool {
+        pos[0] >= 0 && pos[0] < self.size[0] && pos[1] >= 0 && pos[1] < self.size[1]
+    }
+
+    fn cell_exists(&self, pos: [i32; 2]) -> bool {
+        self.board[pos[0] as usize][pos[1] as usize].is_some()
+    }
+
+    // Rows with every cell filled, highest first
+    fn full_rows(&self) -> Vec<i32> {
+        let mut full_rows : Vec<i32> = vec![];
+
+        for y in 0..self.size[1] {
+            for x in 0..self.size[0] {
+                if self.board[x as usize][y as usize].is_none() {
+                    break;
+                }
+                if x == self.size[0] - 1 {
+                    full_rows.push(y);
+                }
+            }        
+        }
+
+        full_rows.reverse();
+        full_rows
+    }
+
+    // Removes 'rows', highest first, dropping everything above each one down
+    fn collapse_rows(&mut self, rows: &[i32]) {
+        for y in rows.iter().copied() {
+            for x in 0..self.size[0] {
+                self.board[x as usize][y as usize] = None;
+            }
+            for y_above in (y + 1)..self.size[1] {
+                for x in 0..self.size[0] {
+                    if let Some(block) = self.board[x as usize][y_above as usize].take() {
+                        self.board[x as usize][(y_above - 1) as usize] = Some(block);
+                    }
+                }
+            }
+        }
+    }
+
+    // Taps move straight away, holding is left to 'AutoShift'
+    fn press_shift(&mut self, dir: Dir) {
+        if self.shift.press(dir) && self.piece_active() {
+            self.move_tetrimino(if dir == Dir::Left { [-1, 0] } else { [1, 0] });
+        }
+    }
+
+    fn input(&mut self, input: Input) {
+        match input {
+            Input::Press(Action::MoveLeft) => self.press_shift(Dir::Left),
+            Input::Release(Action::MoveLeft) => self.shift.release(Dir::Left),
+            Input::Press(Action::MoveRight) => self.press_shift(Dir::Right),
+            Input::Release(Action::MoveRight) => self.shift.release(Dir::Right),
+            Input::Press(Action::SoftDrop) => self.soft_drop = true,
+            Input::Release(Action::SoftDrop) => self.soft_drop = false,
+            Input::Press(Action::RotateClockwise) => {
+                self.rotate_tetrimino(Rotation::Clockwise);
+            },
+            Input::Press(Action::RotateCounterClockwise) => {
+                self.rotate_tetrimino(Rotation::CounterClockwise);
+            },
+            Input::Press(Action::Rotate180) => {
+                self.rotate_tetrimino(Rotation::Half);
+            },
+            Input::Press(Action::Hold) => {
+                self.hold_tetrimino();
+            },
+            Input::Press(Action::HardDrop) => {
+                self.hard_drop();
+            },
+            Input::Press(Action::SonicDrop) => {
+                self.sonic_drop();
+            },
+            Input::Release(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mode::Mode;
+    use scoring::ClearResult;
+
+    fn press(action: Action) -> InputFrame {
+        let mut frame = InputFrame::default();
+        frame.press(action);
+        frame
+    }
+
+    fn tap(action: Action) -> InputFrame {
+        let mut frame = press(action);
+        frame.release(action);
+        frame
+    }
+
+    // Fills the board from rows drawn top first, 'X' for garbage, with the last row on the floor
+    fn fill(state: &mut GameState, rows: &[&str]) {
+        for (y, row) in rows.iter().rev().enumerate() {
+            for (x, cell) in row.chars().enumerate() {
+                state.board[x][y] = (cell == 'X').then_some(Block { kind: BlockKind::Garbage, locked_at: 0.0 });
+            }
+        }
+    }
+
+    fn spawn_t(state: &mut GameState) {
+        let t = state.pieces().find("T").unwrap();
+        state.spawn_tetrimino(t);
+    }
+
+    // Steps without input until the active piece locks
+    fn frames_to_lock(state: &mut GameState) -> usize {
+        for frame in 1..=120 {
+            state.step(FRAME_TIME, &InputFrame::default());
+            if state.drain_events().iter().any(|event| matches!(event, GameEvent::Lock(_))) {
+                return frame
+            }
+        }
+        panic!("The piece never locked")
+    }
+
+    #[test]
+    fn srs_kicks_a_t_spin_triple() {
+        let mut state = GameState::new(Settings::default(), 1);
+        fill(&mut state, &[
+            ".X........",
+            "..........",
+            "X.XXXXXXXX",
+            "X..XXXXXXX",
+            "X.XXXXXXXX"
+        ]);
+        // Slid under the overhang, resting on the stack
+        spawn_t(&mut state);
+        state.pos = [1, 2];
+
+        state.step(FRAME_TIME, &tap(Action::RotateClockwise));
+        // Only the last 0 -> R offset, one left and two down, fits
+        assert_eq!((state.rotation, state.pos), (RotationState::Right, [0, 0]));
+
+        state.step(FRAME_TIME, &press(Action::HardDrop));
+        let events = state.drain_events();
+        assert!(events.contains(&GameEvent::Spin { kind: state.pieces().find("T").unwrap(), spin: Spin::Full, lines: 3 }));
+    }
+
+    // Plays at 20G whatever the level
+    struct TwentyG;
+
+    impl GameMode for TwentyG {
+        fn name(&self) -> String {
+            "20G".to_string()
+        }
+
+        fn timings(&self, timings: Timings) -> Timings {
+            Timings { gravity: FRAME_TIME / 20.0, ..timings }
+        }
+
+        fn hud(&self, _scoring: &Scoring, _time: f64) -> Vec<Stat> {
+            vec![]
+        }
+    }
+
+    #[test]
+    fn hold_once_per_piece() {
+        let mut state = GameState::new(Settings::default(), 1);
+        let (first, second) = (state.kind, state.next_pieces()[0]);
+
+        state.step(FRAME_TIME, &press(Action::Hold));
+        assert_eq!((state.held, state.kind), (Some(first), second));
+        // Holding again is refused until the piece locks
+        state.step(FRAME_TIME, &press(Action::Hold));
+        assert_eq!((state.held, state.kind), (Some(first), second));
+
+        state.step(FRAME_TIME, &press(Action::HardDrop));
+        let third = state.kind;
+        state.step(FRAME_TIME, &press(Action::Hold));
+        assert_eq!((state.held, state.kind), (Some(third), first));
+    }
+
+    #[test]
+    fn hold_modes() {
+        let mut state = GameState::new(Settings { hold: HoldMode::Disabled, ..Settings::default() }, 1);
+        let first = state.kind;
+        state.step(FRAME_TIME, &press(Action::Hold));
+        assert_eq!((state.held, state.kind), (None, first));
+
+        let mut state = GameState::new(Settings { hold: HoldMode::Infinite, ..Settings::default() }, 1);
+        let (first, second) = (state.kind, state.next_pieces()[0]);
+        state.step(FRAME_TIME, &tap(Action::Hold));
+        state.step(FRAME_TIME, &tap(Action::Hold));
+        assert_eq!((state.held, state.kind), (Some(second), first));
+    }
+
+    #[test]
+    fn queue_deals_in_order() {
+        let mut state = GameState::new(Settings { preview: 3, ..Settings::default() }, 1);
+        let queue = state.next_pieces().to_vec();
+        assert_eq!(queue.len(), 3);
+
+        state.step(FRAME_TIME, &press(Action::HardDrop));
+        assert_eq!(state.kind, queue[0]);
+        assert_eq!(state.next_pieces()[..2], queue[1..]);
+        assert_eq!(state.next_pieces().len(), 3);
+
+        // The preview stops at 'MAX_PREVIEW' whatever the settings ask for
+        let state = GameState::new(Settings { preview: 20, ..Settings::default() }, 1);
+        assert_eq!(state.next_pieces().len(), MAX_PREVIEW);
+    }
+
+    #[test]
+    fn lock_delay_after_a_move() {
+        for (lock_reset, restarted) in [(LockReset::Move, true), (LockReset::Step, false), (LockReset::Never, false)] {
+            let mut state = GameState::new(Settings { lock_reset, ..Settings::default() }, 1);
+            spawn_t(&mut state);
+            state.step(FRAME_TIME, &press(Action::SonicDrop));
+            for _ in 0..20 {
+                state.step(FRAME_TIME, &InputFrame::default());
+            }
+
+            state.step(FRAME_TIME, &tap(Action::MoveLeft));
+            // 30 frames of lock delay, of which 21 had passed unless the move restarted them
+            assert_eq!(frames_to_lock(&mut state) > 20, restarted, "{:?}", lock_reset);
+        }
+    }
+
+    #[test]
+    fn lock_delay_after_stepping_down() {
+        for (lock_reset, restarted) in [(LockReset::Move, true), (LockReset::Step, true), (LockReset::Never, false)] {
+            let mut state = GameState::new(Settings { lock_reset, ..Settings::default() }, 1);
+            // A ledge the T spawns above and can be shifted off
+            fill(&mut state, &["...XXXXXXX"; 10]);
+            spawn_t(&mut state);
+            state.step(FRAME_TIME, &press(Action::SonicDrop));
+            for _ in 0..20 {
+                state.step(FRAME_TIME, &InputFrame::default());
+            }
+
+            for _ in 0..3 {
+                state.step(FRAME_TIME, &tap(Action::MoveLeft));
+            }
+            state.step(FRAME_TIME, &press(Action::SonicDrop));
+            assert_eq!(frames_to_lock(&mut state) > 20, restarted, "{:?}", lock_reset);
+        }
+    }
+
+    #[test]
+    fn clearing_the_board_is_a_perfect_clear() {
+        let mut state = GameState::new(Settings::default(), 1);
+        fill(&mut state, &["XXX....XXX"]);
+        let i = state.pieces().find("I").unwrap();
+        state.spawn_tetrimino(i);
+
+        state.step(FRAME_TIME, &press(Action::HardDrop));
+        let events = state.drain_events();
+        assert!(events.iter().any(|event| matches!(event, GameEvent::Lock(ClearResult { lines: 1, perfect_clear: true, .. }))));
+    }
+
+    #[test]
+    fn dig_keeps_the_garbage_topped_up() {
+        let state = GameState::new(Settings { mode: Mode::Dig { lines: 4 }, ..Settings::default() }, 1);
+        assert_eq!(state.garbage_rows, 4);
+
+        let mut state = GameState::new(Settings { mode: Mode::Dig { lines: 20 }, ..Settings::default() }, 1);
+        assert_eq!(state.garbage_rows, 10);
+
+        // Clearing the top garbage row brings up another from below
+        let hole = (0..10).find(|x| state.board[*x][9].is_none()).unwrap();
+        let i = state.pieces().find("I").unwrap();
+        state.spawn_tetrimino(i);
+        state.step(FRAME_TIME, &press(Action::RotateClockwise));
+        state.pos[0] = hole as i32 - 2;
+        state.step(FRAME_TIME, &press(Action::HardDrop));
+
+        assert_eq!(state.scoring.garbage_cleared, 1);
+        assert_eq!(state.garbage_rows, 10);
+    }
+
+    // Rows of the active piece's lowest and highest cells
+    fn piece_rows(state: &GameState) -> [i32; 2] {
+        let rows: Vec<i32> = (0..state.tetrimino.len() as i32).filter(|y| state.tetrimino[*y as usize].contains(&true)).collect();
+        [state.pos[1] + rows[0], state.pos[1] + rows[rows.len() - 1]]
+    }
+
+    #[test]
+    fn pieces_spawn_in_the_hidden_rows() {
+        // On the first hidden row, then straight down into view when there is room
+        let mut state = GameState::new(Settings::default(), 1);
+        spawn_t(&mut state);
+        assert_eq!(piece_rows(&state)[0], 19);
+
+        fill(&mut state, &["XXXXXXXXX."]);
+        for x in 0..10 {
+            state.board[x].rotate_right(19);
+        }
+        spawn_t(&mut state);
+        assert_eq!((piece_rows(&state)[0], state.game_over()), (20, None));
+
+        // Without hidden rows the piece spawns against the top of the board, then drops the same row
+        let mut state = GameState::new(Settings { hidden_rows: 0, ..Settings::default() }, 1);
+        spawn_t(&mut state);
+        assert_eq!(piece_rows(&state)[1], 18);
+    }
+
+    #[test]
+    fn twenty_g_lands_pieces_at_once() {
+        let settings = Settings { height: 60, hidden_rows: 40, ..Settings::default() };
+        let mut state = GameState::new(settings, 1);
+        state.mode = Box::new(TwentyG);
+
+        state.spawn_next();
+        assert_eq!(state.drop_distance(), 0);
+
+        // Shifting off a ledge falls straight to the floor in the same step
+        for x in 0..state.size[0] as usize / 2 {
+            for y in 0..30 {
+                state.board[x][y] = Some(Block { kind: BlockKind::Garbage, locked_at: 0.0 });
+            }
+        }
+        state.spawn_next();
+        state.step(FRAME_TIME, &press(Action::MoveRight));
+        state.step(FRAME_TIME, &press(Action::MoveRight));
+        state.step(FRAME_TIME, &press(Action::MoveRight));
+        assert_eq!(state.drop_distance(), 0);
+    }
+
+    #[test]
+    fn master_has_no_ghost() {
+        let state = GameState::new(Settings { mode: Mode::Master, ..Settings::default() }, 1);
+        assert_eq!(state.ghost_pos(), None);
+    }
+
+    #[test]
+    fn hidden_stacks_have_no_ghost() {
+        assert!(GameState::new(Settings::default(), 1).ghost_pos().is_some());
+        for fade in [0.0, 5.0] {
+            let state = GameState::new(Settings { fade: Some(fade), ..Settings::default() }, 1);
+            assert_eq!(state.ghost_pos(), None);
+        }
+    }
+
+    #[test]
+    fn master_only_resets_the_lock_on_a_step() {
+        let mut state = GameState::new(Settings { mode: Mode::Master, ..Settings::default() }, 1);
+        state.step(FRAME_TIME, &press(Action::SonicDrop));
+        state.drain_events();
+
+        // Under 'LockReset::Move' this would keep the piece alive well past the 30 frame lock delay
+        for frame in 0..40 {
+            let action = if frame % 2 == 0 { Action::MoveLeft } else { Action::MoveRight };
+            state.step(FRAME_TIME, &press(action));
+        }
+        assert!(state.drain_events().iter().any(|event| matches!(event, GameEvent::Lock(_))));
+    }
+
+    #[test]
+    fn game_over_during_inputs_ends_the_step() {
+        let mut state = GameState::new(Settings::default(), 1);
+        for x in 1..state.size[0] as usize {
+            state.board[x][state.visible_rows as usize - 1] = Some(Block { kind: BlockKind::Garbage, locked_at: 0.0 });
+        }
+        // The next piece can't drop into view, so a hard drop locks it above the skyline
+        state.spawn_next();
+        // Bring the grounded piece close to its lock delay, so the rest of the step would lock it again
+        for _ in 0..29 {
+            state.step(FRAME_TIME, &InputFrame::default());
+        }
+
+        state.step(FRAME_TIME, &press(Action::HardDrop));
+
+        let events = state.drain_events();
+        assert_eq!(events.iter().filter(|event| matches!(event, GameEvent::Lock(_))).count(), 1);
+        assert_eq!(events.last(), Some(&GameEvent::GameOver(GameOver::TopOut(TopOut::LockOut))));
+    }
+}
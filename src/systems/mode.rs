@@ -0,0 +1,438 @@
+use super::{
+    scoring::{ClearResult, Scoring},
+    settings::{LockReset, Timings},
+    GameOver, FRAME_TIME
+};
+
+// What a game is played for. Each mode decides when the game is won besides
+// topping out, and which numbers are shown while playing and afterwards.
+pub trait GameMode {
+    // Shown in the window title and on the results screen
+    fn name(&self) -> String;
+
+    // Called after every lock, before the next piece spawns
+    fn lock(&mut self, _result: &ClearResult, _scoring: &Scoring, _time: f64) -> Option<GameOver> {
+        None
+    }
+
+    // Called at the start of every step once the clock has advanced
+    fn tick(&mut self, _scoring: &Scoring, _time: f64) -> Option<GameOver> {
+        None
+    }
+
+    // Garbage rows to add beneath the stack at the start and after every lock,
+    // given how many are already on the board
+    fn garbage(&mut self, _scoring: &Scoring, _on_board: usize) -> usize {
+        0
+    }
+
+    // Takes the timings from the settings and the level's gravity, and returns the ones to play by
+    fn timings(&self, timings: Timings) -> Timings {
+        timings
+    }
+
+    // Modes can hide the ghost whatever the settings say
+    fn ghost(&self) -> bool {
+        true
+    }
+
+    fn hud(&self, scoring: &Scoring, time: f64) -> Vec<Stat>;
+
+    fn results(&self, scoring: &Scoring, time: f64) -> Vec<Stat> {
+        self.hud(scoring, time)
+    }
+}
+
+// A labelled value for the HUD or the results screen
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Stat {
+    pub label: String,
+    pub value: String
+}
+
+impl Stat {
+    fn new(label: impl Into<String>, value: impl ToString) -> Self {
+        Stat {
+            label: label.into(),
+            value: value.to_string()
+        }
+    }
+}
+
+pub const MARATHON_LEVELS: u32 = 15;
+pub const SPRINT_LINES: [u32; 4] = [20, 40, 100, 1000];
+// Split times are taken this many times over a sprint, the last at the finish
+pub const SPRINT_SPLITS: u32 = 4;
+pub const ULTRA_SECONDS: u32 = 120;
+pub const DIG_LINES: u32 = 10;
+// Garbage rows kept on the board while digging, fewer once the goal is closer
+const DIG_ROWS: u32 = 10;
+
+pub const MASTER_LEVELS: u32 = 999;
+
+// TGM2+ Master gravity in 1/256ths of a row per frame, each from the level it starts at.
+// 5120 is 20G, the piece lands the moment it appears
+const MASTER_GRAVITY: [(u32, u32); 30] = [
+    (0, 4), (30, 6), (35, 8), (40, 10), (50, 12), (60, 16), (70, 32), (80, 48), (90, 64), (100, 80),
+    (120, 96), (140, 112), (160, 128), (170, 144), (200, 4), (220, 32), (230, 64), (233, 96), (236, 128), (239, 160),
+    (243, 192), (247, 224), (251, 256), (300, 512), (330, 768), (360, 1024), (400, 1280), (420, 1024), (450, 768), (500, 5120)
+];
+
+// Frames of entry delay, line clear delay, DAS and lock delay, each from the level they start at
+const MASTER_TIMINGS: [(u32, [u32; 4]); 6] = [
+    (0, [25, 40, 14, 30]),
+    (500, [25, 25, 8, 30]),
+    (600, [25, 16, 8, 30]),
+    (700, [16, 12, 8, 30]),
+    (800, [12, 6, 8, 30]),
+    (900, [12, 6, 6, 17])
+];
+
+// TGM grades and the score each one needs
+const GRADES: [(&str, u32); 18] = [
+    ("9", 0), ("8", 400), ("7", 800), ("6", 1400), ("5", 2000), ("4", 3500), ("3", 5500), ("2", 8000), ("1", 12000),
+    ("S1", 16000), ("S2", 22000), ("S3", 30000), ("S4", 40000), ("S5", 52000), ("S6", 66000), ("S7", 82000),
+    ("S8", 100000), ("S9", 120000)
+];
+// Finishing at the top grade inside this many seconds makes Grand Master
+const GRAND_MASTER_SECONDS: f64 = 13.0 * 60.0 + 30.0;
+
+// Picks the game mode, kept plain so it can live in 'Settings' and be saved with replays
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    // Ends once 'levels' levels are cleared, or never
+    Marathon { levels: Option<u32> },
+    // Race to clear 'lines' lines, one of 'SPRINT_LINES'
+    Sprint { lines: u32 },
+    // Score as much as possible in 'seconds'
+    Ultra { seconds: u32 },
+    // Clear 'lines' rows of garbage, refilled from below as they go
+    Dig { lines: u32 },
+    // TGM style, levels up to 'MASTER_LEVELS' with the timings tightening to 20G
+    Master
+}
+
+impl Default for Mode {
+    fn default() -> Self {
+        Mode::Marathon { levels: None }
+    }
+}
+
+impl Mode {
+    pub fn game_mode(self) -> Box<dyn GameMode> {
+        match self {
+            Mode::Marathon { levels } => Box::new(Marathon { levels }),
+            Mode::Sprint { lines } => Box::new(Sprint::new(lines)),
+            Mode::Ultra { seconds } => Box::new(Ultra { seconds }),
+            Mode::Dig { lines } => Box::new(Dig { lines }),
+            Mode::Master => Box::new(Master::new())
+        }
+    }
+}
+
+pub struct Marathon {
+    levels: Option<u32>
+}
+
+impl GameMode for Marathon {
+    fn name(&self) -> String {
+        match self.levels {
+            Some(_) => "Marathon".to_string(),
+            None => "Endless".to_string()
+        }
+    }
+
+    // Levels count from the start level, so starting higher doesn't shorten the game
+    fn lock(&mut self, _result: &ClearResult, scoring: &Scoring, _time: f64) -> Option<GameOver> {
+        let levels = self.levels?;
+        (scoring.level - scoring.start_level() >= levels).then_some(GameOver::Finished)
+    }
+
+    fn hud(&self, scoring: &Scoring, time: f64) -> Vec<Stat> {
+        vec![
+            Stat::new("SCORE", scoring.score),
+            Stat::new("LEVEL", scoring.level),
+            Stat::new("LINES", scoring.lines),
+            Stat::new("TIME", format_time(time))
+        ]
+    }
+}
+
+pub struct Sprint {
+    lines: u32,
+    // When each multiple of 'split_lines' was reached
+    splits: Vec<f64>
+}
+
+impl Sprint {
+    pub fn new(lines: u32) -> Self {
+        Sprint {
+            lines,
+            splits: vec![]
+        }
+    }
+
+    fn split_lines(&self) -> u32 {
+        (self.lines / SPRINT_SPLITS).max(1)
+    }
+}
+
+impl GameMode for Sprint {
+    fn name(&self) -> String {
+        format!("Sprint {}", self.lines)
+    }
+
+    fn lock(&mut self, _result: &ClearResult, scoring: &Scoring, time: f64) -> Option<GameOver> {
+        while (self.splits.len() as u32 + 1) * self.split_lines() <= scoring.lines.min(self.lines) {
+            self.splits.push(time);
+        }
+
+        (scoring.lines >= self.lines).then_some(GameOver::Finished)
+    }
+
+    fn hud(&self, scoring: &Scoring, time: f64) -> Vec<Stat> {
+        let mut stats = vec![
+            Stat::new("TIME", format_time(time)),
+            Stat::new("LINES", format!("{}/{}", scoring.lines.min(self.lines), self.lines))
+        ];
+
+        for (i, split) in self.splits.iter().enumerate() {
+            stats.push(Stat::new((self.split_lines() * (i as u32 + 1)).to_string(), format_time(*split)));
+        }
+
+        stats
+    }
+}
+
+pub struct Ultra {
+    seconds: u32
+}
+
+impl GameMode for Ultra {
+    fn name(&self) -> String {
+        format!("Ultra {}", format_duration(self.seconds))
+    }
+
+    fn tick(&mut self, _scoring: &Scoring, time: f64) -> Option<GameOver> {
+        (time >= self.seconds as f64).then_some(GameOver::Finished)
+    }
+
+    fn hud(&self, scoring: &Scoring, time: f64) -> Vec<Stat> {
+        vec![
+            Stat::new("TIME", format_time((self.seconds as f64 - time).max(0.0))),
+            Stat::new("SCORE", scoring.score),
+            Stat::new("LINES", scoring.lines)
+        ]
+    }
+
+    // Ranked by score, the time left is always zero by now
+    fn results(&self, scoring: &Scoring, _time: f64) -> Vec<Stat> {
+        vec![
+            Stat::new("SCORE", scoring.score),
+            Stat::new("LINES", scoring.lines),
+            Stat::new("LEVEL", scoring.level)
+        ]
+    }
+}
+
+pub struct Dig {
+    lines: u32
+}
+
+impl GameMode for Dig {
+    fn name(&self) -> String {
+        format!("Dig {}", self.lines)
+    }
+
+    fn lock(&mut self, _result: &ClearResult, scoring: &Scoring, _time: f64) -> Option<GameOver> {
+        (scoring.garbage_cleared >= self.lines).then_some(GameOver::Finished)
+    }
+
+    fn garbage(&mut self, scoring: &Scoring, on_board: usize) -> usize {
+        let remaining = self.lines.saturating_sub(scoring.garbage_cleared);
+        (DIG_ROWS.min(remaining) as usize).saturating_sub(on_board)
+    }
+
+    fn hud(&self, scoring: &Scoring, time: f64) -> Vec<Stat> {
+        vec![
+            Stat::new("TIME", format_time(time)),
+            Stat::new("DUG", format!("{}/{}", scoring.garbage_cleared.min(self.lines), self.lines)),
+            Stat::new("LINES", scoring.lines)
+        ]
+    }
+}
+
+// Levels go up by one for every piece and every line cleared. Only clears can move past
+// the last level of each hundred, and the grade follows TGM's own score.
+pub struct Master {
+    level: u32,
+    score: u32,
+    combo: u32,
+    grand_master: bool
+}
+
+impl Master {
+    pub fn new() -> Self {
+        Master {
+            level: 0,
+            score: 0,
+            combo: 1,
+            grand_master: false
+        }
+    }
+
+    fn grade(&self) -> &'static str {
+        if self.grand_master { return "GM" }
+
+        GRADES.iter().rev().find(|(_, score)| self.score >= *score).map_or(GRADES[0].0, |(grade, _)| grade)
+    }
+
+    // The level the current section stops at
+    fn section_end(&self) -> u32 {
+        (self.level / 100 * 100 + 100).min(MASTER_LEVELS)
+    }
+}
+
+impl Default for Master {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameMode for Master {
+    fn name(&self) -> String {
+        "Master".to_string()
+    }
+
+    // TGM scoring, without the soft drop bonus: the combo grows with each clear and
+    // a perfect clear counts four times
+    fn lock(&mut self, result: &ClearResult, _scoring: &Scoring, time: f64) -> Option<GameOver> {
+        let lines = result.lines as u32;
+
+        if lines > 0 {
+            self.combo += 2 * lines - 2;
+            let bravo = if result.perfect_clear { 4 } else { 1 };
+            self.score += (self.level + lines).div_ceil(4) * lines * self.combo * bravo;
+            self.level = (self.level + lines).min(MASTER_LEVELS);
+        } else {
+            self.combo = 1;
+        }
+
+        if self.level >= MASTER_LEVELS {
+            self.grand_master = self.grade() == GRADES[GRADES.len() - 1].0 && time <= GRAND_MASTER_SECONDS;
+            return Some(GameOver::Finished)
+        }
+
+        // Counts the next piece, which can't take the level past a section stop
+        if self.level + 1 < self.section_end() {
+            self.level += 1;
+        }
+        None
+    }
+
+    fn timings(&self, _timings: Timings) -> Timings {
+        let gravity = table(&MASTER_GRAVITY, self.level);
+        let [entry_delay, line_clear_delay, das, lock_delay] = table(&MASTER_TIMINGS, self.level);
+
+        Timings {
+            gravity: FRAME_TIME * 256.0 / gravity as f32,
+            das: das as f32 * FRAME_TIME,
+            lock_delay: lock_delay as f32 * FRAME_TIME,
+            // Only stepping down restarts the lock timer, so pieces can't be spun forever at 20G
+            lock_reset: LockReset::Step,
+            entry_delay: entry_delay as f32 * FRAME_TIME,
+            line_clear_delay: line_clear_delay as f32 * FRAME_TIME
+        }
+    }
+
+    // Like the arcade game, Master is played without a ghost
+    fn ghost(&self) -> bool {
+        false
+    }
+
+    fn hud(&self, _scoring: &Scoring, time: f64) -> Vec<Stat> {
+        vec![
+            Stat::new("GRADE", self.grade()),
+            Stat::new("LEVEL", format!("{}/{}", self.level, self.section_end())),
+            Stat::new("SCORE", self.score),
+            Stat::new("TIME", format_time(time))
+        ]
+    }
+
+    fn results(&self, _scoring: &Scoring, time: f64) -> Vec<Stat> {
+        vec![
+            Stat::new("GRADE", self.grade()),
+            Stat::new("LEVEL", self.level),
+            Stat::new("SCORE", self.score),
+            Stat::new("TIME", format_time(time))
+        ]
+    }
+}
+
+// The entry of a table keyed by starting level that applies at 'level'
+fn table<T: Copy>(table: &[(u32, T)], level: u32) -> T {
+    table.iter().rev().find(|(from, _)| level >= *from).unwrap_or(&table[0]).1
+}
+
+// Minutes, seconds and milliseconds, e.g. 1:02.345
+pub fn format_time(seconds: f64) -> String {
+    let millis = (seconds * 1000.0).round() as u64;
+
+    format!("{}:{:02}.{:03}", millis / 60_000, millis / 1000 % 60, millis % 1000)
+}
+
+// Whole minutes and seconds, e.g. 2:00
+fn format_duration(seconds: u32) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::spin::Spin;
+
+    #[test]
+    fn sprint_splits_every_quarter() {
+        let mut sprint = Sprint::new(40);
+        let mut scoring = Scoring::new(1);
+
+        for (time, lines) in [(1.0, 4), (2.0, 4), (3.0, 4), (4.0, 4), (5.0, 4)] {
+            let result = scoring.lock(lines, Spin::None, false);
+            assert_eq!(sprint.lock(&result, &scoring, time), None);
+        }
+        assert_eq!(sprint.splits, [3.0, 5.0]);
+
+        // A clear past several splits takes them all at once, and the last one finishes the race
+        scoring.lines = 36;
+        let result = scoring.lock(4, Spin::None, false);
+        assert_eq!(sprint.lock(&result, &scoring, 6.0), Some(GameOver::Finished));
+        assert_eq!(sprint.splits, [3.0, 5.0, 6.0, 6.0]);
+
+        let labels: Vec<String> = sprint.hud(&scoring, 6.0).into_iter().skip(2).map(|stat| stat.label).collect();
+        assert_eq!(labels, ["10", "20", "30", "40"]);
+    }
+
+    #[test]
+    fn ultra_ends_on_time() {
+        let mut ultra = Ultra { seconds: ULTRA_SECONDS };
+        let scoring = Scoring::new(1);
+
+        assert_eq!(ultra.tick(&scoring, 119.9), None);
+        assert_eq!(ultra.tick(&scoring, 120.0), Some(GameOver::Finished));
+    }
+
+    #[test]
+    fn marathon_counts_levels_from_the_start_level() {
+        let mut marathon = Marathon { levels: Some(MARATHON_LEVELS) };
+        let mut scoring = Scoring::new(3);
+        let result = scoring.lock(1, Spin::None, false);
+
+        scoring.level = 17;
+        assert_eq!(marathon.lock(&result, &scoring, 0.0), None);
+        scoring.level = 18;
+        assert_eq!(marathon.lock(&result, &scoring, 0.0), Some(GameOver::Finished));
+
+        // Endless never finishes
+        assert_eq!(Marathon { levels: None }.lock(&result, &scoring, 0.0), None);
+    }
+}
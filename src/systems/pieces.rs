@@ -0,0 +1,318 @@
+use std::iter::Peekable;
+
+use anyhow::*;
+
+use super::rotation::{kick_preset, rotate_cells, Kicks, Rotation, RotationState};
+
+// Piece sets are plain text, one setting per line:
+//
+//   # Comments run to the end of the line
+//   piece T                starts a new piece, each name used once
+//   color 0.65 0.15 0.85   red, green and blue from 0 to 1
+//   shape                  rows of a square box, top first, 'X' filled and '.' empty
+//   .X.
+//   XXX
+//   ...
+//   shape right            the other rotation states, 'right', 'reverse' and 'left', are
+//                          the spawn shape rotated unless given
+//   kicks srs              a table from 'rotation::kick_preset', 'none' when left out
+//   kick 0 R 0,0 -1,0      the offsets tried for one rotation between states 0, R, 2 and L
+//   spawn -1 0             moves the piece from its centred spawn position
+//   spin corners           judges spins by the T's three corner rule, for 3x3 boxes
+//
+// 'pieces/standard.pieces' holds the guideline set in this format
+
+const STANDARD: &str = include_str!("../../pieces/standard.pieces");
+
+// Largest box a shape can have
+const MAX_SHAPE_SIZE: usize = 8;
+
+// A piece's position in its set
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PieceId(pub usize);
+
+#[derive(Clone, Debug)]
+pub struct Piece {
+    pub name: String,
+    pub color: [f32; 3],
+    // Offset from the centred spawn position
+    pub spawn: [i32; 2],
+    pub corner_spins: bool,
+    // Indexed by 'RotationState::index', bottom row first to match the board
+    states: [Vec<Vec<bool>>; 4],
+    kicks: Kicks
+}
+
+impl Piece {
+    pub fn cells(&self, state: RotationState) -> &[Vec<bool>] {
+        &self.states[state.index()]
+    }
+
+    pub fn kicks(&self, from: RotationState, rotation: Rotation) -> &[[i32; 2]] {
+        &self.kicks[from.index()][rotation.index()]
+    }
+
+    // Columns the filled cells span in the piece's widest rotation state
+    pub fn width(&self) -> usize {
+        self.states.iter().map(|cells| {
+            let [first, last] = filled_columns(cells);
+            last + 1 - first
+        }).max().unwrap_or_default()
+    }
+
+    // Left edge of the box on a board 'board_width' columns across, centred rounding left then moved by 'spawn'
+    pub fn spawn_x(&self, board_width: i32) -> i32 {
+        (board_width - self.states[0].len() as i32) / 2 + self.spawn[0]
+    }
+}
+
+// The first and last columns of a box with filled cells, shapes always have one
+fn filled_columns(cells: &[Vec<bool>]) -> [usize; 2] {
+    let columns: Vec<usize> = (0..cells.len()).filter(|&x| cells.iter().any(|row| row[x])).collect();
+    [columns[0], columns[columns.len() - 1]]
+}
+
+#[derive(Debug)]
+pub struct PieceSet {
+    pieces: Vec<Piece>,
+    // The text the set was read from, so replays can carry it. 'None' for the standard set
+    source: Option<String>
+}
+
+impl PieceSet {
+    pub fn standard() -> Self {
+        PieceSet {
+            source: None,
+            ..PieceSet::parse(STANDARD).expect("The standard piece set is valid")
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut pieces: Vec<PartialPiece> = vec![];
+        let mut lines = source.lines().enumerate()
+            .map(|(index, line)| (index + 1, line.split('#').next().unwrap_or_default().trim()))
+            .filter(|(_, line)| !line.is_empty())
+            .peekable();
+
+        while let Some((number, line)) = lines.next() {
+            read_line(&mut pieces, line, &mut lines).with_context(|| format!("Line {}", number))?;
+        }
+
+        ensure!(!pieces.is_empty(), "A piece set needs at least one piece");
+
+        Ok(PieceSet {
+            pieces: pieces.into_iter().map(PartialPiece::finish).collect::<Result<_>>()?,
+            source: Some(source.to_string())
+        })
+    }
+
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pieces.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pieces.is_empty()
+    }
+
+    pub fn piece(&self, id: PieceId) -> &Piece {
+        &self.pieces[id.0]
+    }
+
+    pub fn find(&self, name: &str) -> Option<PieceId> {
+        self.pieces.iter().position(|piece| piece.name == name).map(PieceId)
+    }
+
+    // Every rotation state has to fit the board where the piece spawns, or it would block out or
+    // be unable to turn on an empty board
+    pub fn check_width(&self, width: u32) -> Result<()> {
+        for piece in &self.pieces {
+            ensure!(
+                piece.width() <= width as usize,
+                "Piece '{}' is {} cells wide, too wide for a board {} columns across", piece.name, piece.width(), width
+            );
+
+            let x = piece.spawn_x(width as i32);
+            let fits = piece.states.iter().all(|cells| {
+                let [first, last] = filled_columns(cells);
+                let (left, right) = (x + first as i32, x + last as i32);
+                left >= 0 && right < width as i32
+            });
+            ensure!(fits, "Piece '{}' spawns partly off a board {} columns across", piece.name, width);
+        }
+        Ok(())
+    }
+}
+
+// A piece while its lines are being read
+struct PartialPiece {
+    name: String,
+    color: [f32; 3],
+    spawn: [i32; 2],
+    corner_spins: bool,
+    states: [Option<Vec<Vec<bool>>>; 4],
+    kicks: Kicks
+}
+
+impl PartialPiece {
+    fn finish(self) -> Result<Piece> {
+        let spawn_shape = self.states[0].clone().with_context(|| format!("Piece '{}' has no shape", self.name))?;
+        ensure!(!self.corner_spins || spawn_shape.len() == 3, "Piece '{}' needs a 3x3 box for corner spins", self.name);
+
+        // Missing states are turned clockwise from the spawn shape
+        let states = std::array::from_fn(|index| {
+            self.states[index].clone().unwrap_or_else(|| {
+                (0..index).fold(spawn_shape.clone(), |cells, _| rotate_cells(&cells, Rotation::Clockwise))
+            })
+        });
+
+        Ok(Piece {
+            name: self.name,
+            color: self.color,
+            spawn: self.spawn,
+            corner_spins: self.corner_spins,
+            states,
+            kicks: self.kicks
+        })
+    }
+}
+
+fn read_line<'a>(
+    pieces: &mut Vec<PartialPiece>,
+    line: &str,
+    lines: &mut Peekable<impl Iterator<Item = (usize, &'a str)>>
+) -> Result<()> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+
+    if let ["piece", name] = words[..] {
+        ensure!(pieces.iter().all(|piece| piece.name != name), "Piece '{}' is defined twice", name);
+        pieces.push(PartialPiece {
+            name: name.to_string(),
+            color: [1.0, 1.0, 1.0],
+            spawn: [0, 0],
+            corner_spins: false,
+            states: Default::default(),
+            kicks: kick_preset("none").unwrap()
+        });
+        return Ok(())
+    }
+
+    let piece = pieces.last_mut().with_context(|| format!("'{}' comes before any 'piece'", words[0]))?;
+
+    match words[..] {
+        ["color", r, g, b] => {
+            for (channel, value) in piece.color.iter_mut().zip([r, g, b]) {
+                *channel = number(value)?;
+                ensure!((0.0..=1.0).contains(channel), "Colours run from 0 to 1");
+            }
+        },
+        ["shape"] => piece.states[0] = Some(read_shape(lines)?),
+        ["shape", state] => piece.states[rotation_state(state)?.index()] = Some(read_shape(lines)?),
+        ["kicks", preset] => piece.kicks = kick_preset(preset).with_context(|| format!("Unknown kick table '{}'", preset))?,
+        ["kick", from, to, ref offsets @ ..] => {
+            let (from, to) = (rotation_state(from)?, rotation_state(to)?);
+            let rotation = [Rotation::Clockwise, Rotation::Half, Rotation::CounterClockwise].into_iter()
+                .find(|rotation| from.rotate(*rotation) == to)
+                .context("A kick has to change rotation state")?;
+            ensure!(!offsets.is_empty(), "A kick needs at least one offset");
+
+            piece.kicks[from.index()][rotation.index()] = offsets.iter().map(|offset| {
+                let (x, y) = offset.split_once(',').with_context(|| format!("Offsets are 'x,y', not '{}'", offset))?;
+                Ok([number(x)?, number(y)?])
+            }).collect::<Result<_>>()?;
+        },
+        ["spawn", x, y] => piece.spawn = [number(x)?, number(y)?],
+        ["spin", "corners"] => piece.corner_spins = true,
+        _ => bail!("Couldn't read '{}'", line)
+    }
+
+    Ok(())
+}
+
+// Reads a square box top row first, returning it bottom row first
+fn read_shape<'a>(lines: &mut Peekable<impl Iterator<Item = (usize, &'a str)>>) -> Result<Vec<Vec<bool>>> {
+    let mut rows: Vec<Vec<bool>> = vec![];
+
+    while let Some((_, line)) = lines.next_if(|(_, line)| line.chars().all(|c| c == 'X' || c == '.')) {
+        rows.push(line.chars().map(|c| c == 'X').collect());
+        if rows.len() == rows[0].len() { break }
+    }
+
+    let size = rows.len();
+    ensure!(size > 0 && rows.iter().all(|row| row.len() == size), "Shapes must be square boxes of 'X' and '.'");
+    ensure!(size <= MAX_SHAPE_SIZE, "Shapes can be at most {0}x{0}", MAX_SHAPE_SIZE);
+    ensure!(rows.iter().flatten().any(|cell| *cell), "Shapes need at least one filled cell");
+
+    rows.reverse();
+    Ok(rows)
+}
+
+fn rotation_state(name: &str) -> Result<RotationState> {
+    match name {
+        "0" | "spawn" => Ok(RotationState::Spawn),
+        "R" | "right" => Ok(RotationState::Right),
+        "2" | "reverse" => Ok(RotationState::Reverse),
+        "L" | "left" => Ok(RotationState::Left),
+        _ => bail!("Unknown rotation state '{}'", name)
+    }
+}
+
+fn number<T: std::str::FromStr>(value: &str) -> Result<T> {
+    value.parse().ok().with_context(|| format!("Invalid number '{}'", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pieces_must_fit_the_board() {
+        let standard = PieceSet::standard();
+        assert_eq!(standard.piece(standard.find("I").unwrap()).width(), 4);
+        assert!(standard.check_width(4).is_ok());
+
+        let pentominoes = PieceSet::parse(include_str!("../../pieces/pentominoes.pieces")).unwrap();
+        assert!(pentominoes.check_width(4).is_err());
+        assert!(pentominoes.check_width(5).is_ok());
+    }
+
+    #[test]
+    fn pieces_must_fit_where_they_spawn() {
+        // Boxes wider than the board are centred about its middle, so the edges hang off it
+        let edge = PieceSet::parse("piece A\nshape\n....X\n.....\n.....\n.....\n.....").unwrap();
+        assert!(edge.check_width(4).is_err());
+        assert!(edge.check_width(5).is_ok());
+
+        let centre = PieceSet::parse("piece A\nshape\n.....\n.....\n..X..\n.....\n.....").unwrap();
+        assert!(centre.check_width(4).is_ok());
+
+        let moved = PieceSet::parse("piece A\nshape\nXX\nXX\nspawn 5 0").unwrap();
+        assert!(moved.check_width(10).is_err());
+    }
+
+    #[test]
+    fn invalid_sets_say_what_is_wrong() {
+        let cases = [
+            ("", "at least one piece"),
+            ("color 1 1 1", "Line 1: 'color' comes before any 'piece'"),
+            ("piece A\nshape\nX\npiece A\nshape\nX", "Line 4: Piece 'A' is defined twice"),
+            ("piece A", "Piece 'A' has no shape"),
+            ("piece A\nshape\nXX\nX", "Shapes must be square boxes"),
+            ("piece A\nshape\n..\n..", "at least one filled cell"),
+            ("piece A\ncolor 2 0 0", "Colours run from 0 to 1"),
+            ("piece A\nkicks sideways", "Unknown kick table 'sideways'"),
+            ("piece A\nkick 0 0 0,0", "has to change rotation state"),
+            ("piece A\nkick 0 R 1;0", "Offsets are 'x,y'"),
+            ("piece A\nshape\nXX\nXX\nspin corners", "needs a 3x3 box for corner spins"),
+            ("piece A\nwobble", "Couldn't read 'wobble'")
+        ];
+
+        for (source, message) in cases {
+            let error = PieceSet::parse(source).expect_err(source);
+            assert!(format!("{:#}", error).contains(message), "'{:#}' doesn't mention '{}'", error, message);
+        }
+    }
+}
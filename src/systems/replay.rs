@@ -0,0 +1,464 @@
+use std::sync::Arc;
+
+use anyhow::*;
+
+use super::{
+    generator::Randomizer,
+    input::{Action, Input, InputFrame},
+    mode::Mode,
+    pieces::PieceSet,
+    settings::{HoldMode, LockReset, Settings, BOARD_HEIGHTS, BOARD_WIDTHS, MAX_HIDDEN_ROWS, MAX_START_LEVEL},
+    GameState, FRAME_TIME
+};
+
+// A replay is the seed, the settings and every input tagged with the frame it went in on.
+// The engine is deterministic, so that is all it takes to play a game back exactly.
+//
+// File layout, little endian:
+//   magic, version, seed, settings, frame count,
+//   input count then per input a varint frame delta and an action code
+
+const MAGIC: &[u8; 4] = b"TRRP";
+pub const REPLAY_VERSION: u8 = 1;
+
+// Set on an action code when the input is a press rather than a release
+const PRESS_FLAG: u8 = 0x80;
+
+#[derive(Clone, Debug)]
+pub struct Replay {
+    pub seed: u64,
+    pub settings: Settings,
+    // Number of steps the game ran for
+    pub frames: u32,
+    pub inputs: Vec<(u32, Input)>
+}
+
+impl Replay {
+    pub fn new(seed: u64, settings: Settings) -> Self {
+        Replay {
+            seed,
+            settings,
+            frames: 0,
+            inputs: vec![]
+        }
+    }
+
+    // Called once per step with that step's inputs
+    pub fn record(&mut self, frame: &InputFrame) {
+        for input in &frame.inputs {
+            self.inputs.push((self.frames, *input));
+        }
+        self.frames += 1;
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(REPLAY_VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        write_settings(&mut bytes, &self.settings);
+        bytes.extend_from_slice(&self.frames.to_le_bytes());
+
+        write_varint(&mut bytes, self.inputs.len() as u32);
+        let mut previous = 0;
+        for (frame, input) in &self.inputs {
+            write_varint(&mut bytes, frame - previous);
+            bytes.push(input_code(*input));
+            previous = *frame;
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader { bytes, pos: 0 };
+
+        ensure!(reader.take(MAGIC.len())? == MAGIC, "Not a replay file");
+        let version = reader.u8()?;
+        ensure!(version == REPLAY_VERSION, "Unsupported replay version {}", version);
+
+        let seed = reader.u64()?;
+        let settings = read_settings(&mut reader)?;
+        check_settings(&settings)?;
+        let frames = reader.u32()?;
+
+        let count = reader.varint()?;
+        // Every input takes at least two bytes, so a corrupt count can't ask for more than the file holds
+        let mut inputs = Vec::with_capacity((count as usize).min(reader.remaining() / 2));
+        let mut frame: u32 = 0;
+        for _ in 0..count {
+            frame = frame.checked_add(reader.varint()?).context("Replay input frames overflow")?;
+            inputs.push((frame, decode_input(reader.u8()?)?));
+        }
+
+        Ok(Replay { seed, settings, frames, inputs })
+    }
+}
+
+// Feeds a replay's inputs back into a fresh game
+pub struct Playback {
+    replay: Replay,
+    state: GameState,
+    frame: u32,
+    next_input: usize,
+    accumulator: f32,
+    pub paused: bool,
+    pub speed: f32
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        let state = GameState::new(replay.settings.clone(), replay.seed);
+
+        Playback {
+            replay,
+            state,
+            frame: 0,
+            next_input: 0,
+            accumulator: 0.0,
+            paused: false,
+            speed: 1.0
+        }
+    }
+
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    pub fn length(&self) -> u32 {
+        self.replay.frames
+    }
+
+    pub fn finished(&self) -> bool {
+        self.frame >= self.replay.frames
+    }
+
+    // How far playback is between steps, for interpolation
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / FRAME_TIME
+    }
+
+    // Advances by 'elapsed' real seconds scaled by 'speed'
+    pub fn update(&mut self, elapsed: f32) {
+        if self.paused || self.finished() {
+            self.accumulator = 0.0;
+            return
+        }
+
+        self.accumulator += elapsed * self.speed;
+        while self.accumulator >= FRAME_TIME && !self.finished() {
+            self.step();
+            self.accumulator -= FRAME_TIME;
+        }
+    }
+
+    // Jumps to 'frame', going backwards plays the game again from the start
+    pub fn seek(&mut self, frame: u32) {
+        let frame = frame.min(self.replay.frames);
+
+        if frame < self.frame {
+            self.state = GameState::new(self.replay.settings.clone(), self.replay.seed);
+            self.frame = 0;
+            self.next_input = 0;
+        }
+
+        while self.frame < frame {
+            self.step();
+        }
+        self.accumulator = 0.0;
+    }
+
+    fn step(&mut self) {
+        let mut inputs = InputFrame::default();
+
+        while let Some((frame, input)) = self.replay.inputs.get(self.next_input) {
+            if *frame != self.frame { break }
+            inputs.inputs.push(*input);
+            self.next_input += 1;
+        }
+
+        self.state.step(FRAME_TIME, &inputs);
+        self.state.drain_events();
+        self.frame += 1;
+    }
+}
+
+fn code<T: PartialEq>(all: &[T], value: T) -> u8 {
+    all.iter().position(|v| *v == value).unwrap() as u8
+}
+
+fn decode<T: Copy>(all: &[T], code: u8, name: &str) -> Result<T> {
+    all.get(code as usize).copied().with_context(|| format!("Unknown {} {}", name, code))
+}
+
+fn input_code(input: Input) -> u8 {
+    match input {
+        Input::Press(action) => code(&Action::ALL, action) | PRESS_FLAG,
+        Input::Release(action) => code(&Action::ALL, action)
+    }
+}
+
+fn decode_input(code: u8) -> Result<Input> {
+    let action = decode(&Action::ALL, code & !PRESS_FLAG, "action")?;
+
+    Ok(if code & PRESS_FLAG != 0 { Input::Press(action) } else { Input::Release(action) })
+}
+
+fn write_settings(bytes: &mut Vec<u8>, settings: &Settings) {
+    write_mode(bytes, settings.mode);
+    write_pieces(bytes, &settings.pieces);
+    bytes.extend_from_slice(&settings.width.to_le_bytes());
+    bytes.extend_from_slice(&settings.height.to_le_bytes());
+    bytes.extend_from_slice(&settings.hidden_rows.to_le_bytes());
+    bytes.push(code(&Randomizer::ALL, settings.randomizer));
+    bytes.push(code(&HoldMode::ALL, settings.hold));
+    bytes.push(settings.preview as u8);
+    bytes.extend_from_slice(&settings.soft_drop.to_le_bytes());
+    bytes.extend_from_slice(&settings.lock_delay.to_le_bytes());
+    bytes.push(code(&LockReset::ALL, settings.lock_reset));
+    bytes.extend_from_slice(&settings.das.to_le_bytes());
+    bytes.extend_from_slice(&settings.arr.to_le_bytes());
+    bytes.extend_from_slice(&settings.das_cut.to_le_bytes());
+    bytes.extend_from_slice(&settings.entry_delay.to_le_bytes());
+    bytes.extend_from_slice(&settings.line_clear_delay.to_le_bytes());
+    bytes.extend_from_slice(&settings.start_level.to_le_bytes());
+    bytes.push(settings.all_spin as u8);
+    bytes.push(settings.ghost as u8);
+    bytes.push(settings.partial_lock_out as u8);
+    bytes.extend_from_slice(&settings.garbage_messiness.to_le_bytes());
+    bytes.extend_from_slice(&settings.garbage_change.to_le_bytes());
+    bytes.push(settings.fade.is_some() as u8);
+    bytes.extend_from_slice(&settings.fade.unwrap_or(0.0).to_le_bytes());
+}
+
+fn read_settings(reader: &mut Reader) -> Result<Settings> {
+    Ok(Settings {
+        mode: read_mode(reader)?,
+        pieces: read_pieces(reader)?,
+        width: reader.u32()?,
+        height: reader.u32()?,
+        hidden_rows: reader.u32()?,
+        randomizer: decode(&Randomizer::ALL, reader.u8()?, "randomizer")?,
+        hold: decode(&HoldMode::ALL, reader.u8()?, "hold mode")?,
+        preview: reader.u8()? as usize,
+        soft_drop: reader.f32()?,
+        lock_delay: reader.f32()?,
+        lock_reset: decode(&LockReset::ALL, reader.u8()?, "lock reset")?,
+        das: reader.f32()?,
+        arr: reader.f32()?,
+        das_cut: reader.f32()?,
+        entry_delay: reader.f32()?,
+        line_clear_delay: reader.f32()?,
+        start_level: reader.u32()?,
+        all_spin: reader.u8()? != 0,
+        ghost: reader.u8()? != 0,
+        partial_lock_out: reader.u8()? != 0,
+        garbage_messiness: reader.f32()?,
+        garbage_change: reader.f32()?,
+        fade: {
+            let fading = reader.u8()? != 0;
+            let seconds = reader.f32()?;
+            fading.then_some(seconds)
+        }
+    })
+}
+
+// Rejects settings the game can't run with, which only a damaged or hand-made replay would have
+fn check_settings(settings: &Settings) -> Result<()> {
+    ensure!(
+        BOARD_WIDTHS.contains(&settings.width) && BOARD_HEIGHTS.contains(&settings.height) && settings.hidden_rows <= MAX_HIDDEN_ROWS,
+        "Unsupported board size in replay"
+    );
+    ensure!(settings.start_level <= MAX_START_LEVEL, "Replay start level must be at most {}", MAX_START_LEVEL);
+
+    let chances = [settings.garbage_messiness, settings.garbage_change];
+    ensure!(chances.iter().all(|chance| (0.0..=1.0).contains(chance)), "Replay garbage chances must be between 0 and 1");
+
+    let delays = [
+        settings.lock_delay,
+        settings.das,
+        settings.arr,
+        settings.das_cut,
+        settings.entry_delay,
+        settings.line_clear_delay,
+        settings.fade.unwrap_or(0.0)
+    ];
+    ensure!(delays.iter().all(|delay| delay.is_finite() && *delay >= 0.0), "Replay timings must be finite and not negative");
+    // Infinite soft drop is allowed, it drops straight to the floor
+    ensure!(settings.soft_drop > 0.0, "Replay soft drop must be above zero");
+    settings.pieces.check_width(settings.width)?;
+
+    Ok(())
+}
+
+// A tag followed by the mode's parameter
+fn write_mode(bytes: &mut Vec<u8>, mode: Mode) {
+    let (tag, value) = match mode {
+        // An endless marathon is stored as zero levels
+        Mode::Marathon { levels } => (0, levels.unwrap_or(0)),
+        Mode::Sprint { lines } => (1, lines),
+        Mode::Ultra { seconds } => (2, seconds),
+        Mode::Dig { lines } => (3, lines),
+        Mode::Master => (4, 0)
+    };
+    bytes.push(tag);
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn read_mode(reader: &mut Reader) -> Result<Mode> {
+    let tag = reader.u8()?;
+    let value = reader.u32()?;
+
+    match tag {
+        0 => Ok(Mode::Marathon { levels: (value > 0).then_some(value) }),
+        1 => Ok(Mode::Sprint { lines: value }),
+        2 => Ok(Mode::Ultra { seconds: value }),
+        3 => Ok(Mode::Dig { lines: value }),
+        4 => Ok(Mode::Master),
+        _ => bail!("Unknown mode {}", tag)
+    }
+}
+
+// Custom sets are stored as the text they were read from, the standard set as nothing
+fn write_pieces(bytes: &mut Vec<u8>, pieces: &PieceSet) {
+    let source = pieces.source().unwrap_or_default();
+    write_varint(bytes, source.len() as u32);
+    bytes.extend_from_slice(source.as_bytes());
+}
+
+fn read_pieces(reader: &mut Reader) -> Result<Arc<PieceSet>> {
+    let len = reader.varint()? as usize;
+    if len == 0 { return Ok(Arc::new(PieceSet::standard())) }
+
+    let source = std::str::from_utf8(reader.take(len)?).context("Replay piece set isn't text")?;
+    Ok(Arc::new(PieceSet::parse(source).context("Replay piece set")?))
+}
+
+// LEB128, most frame deltas fit in a single byte
+fn write_varint(bytes: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self.bytes.get(self.pos..self.pos + len).context("Replay ended early")?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn f32(&mut self) -> Result<f32> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn varint(&mut self) -> Result<u32> {
+        let mut value = 0;
+        for shift in (0..32).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as u32) << shift;
+            if byte & 0x80 == 0 { return Ok(value) }
+        }
+        bail!("Malformed varint in replay")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replays_play_back_the_same_game() {
+        let settings = Settings {
+            mode: Mode::Sprint { lines: 20 },
+            pieces: Arc::new(PieceSet::parse(include_str!("../../pieces/pentominoes.pieces")).unwrap()),
+            randomizer: Randomizer::Bag14,
+            hold: HoldMode::Infinite,
+            ..Settings::default()
+        };
+        let mut state = GameState::new(settings.clone(), 7);
+        let mut replay = Replay::new(7, settings);
+
+        // Pieces shuffled about and dropped every few frames, enough to score and clear some lines
+        let actions = [Action::MoveLeft, Action::RotateClockwise, Action::MoveRight, Action::MoveRight, Action::Hold];
+        for frame in 0..1200 {
+            let mut inputs = InputFrame::default();
+            if frame % 12 == 11 {
+                inputs.press(Action::HardDrop);
+            } else if frame % 3 == 0 {
+                let action = actions[frame / 3 % actions.len()];
+                inputs.press(action);
+                inputs.release(action);
+            }
+            state.step(FRAME_TIME, &inputs);
+            replay.record(&inputs);
+        }
+        assert!(state.scoring.score > 0);
+
+        let mut playback = Playback::new(Replay::from_bytes(&replay.to_bytes()).unwrap());
+        playback.seek(replay.frames);
+        assert_eq!(playback.state().scoring.score, state.scoring.score);
+        assert_eq!(playback.state().scoring.lines, state.scoring.lines);
+        assert_eq!(playback.state().board, state.board);
+    }
+
+    #[test]
+    fn corrupt_input_count_is_an_error() {
+        let mut bytes = Replay::new(1, Settings::default()).to_bytes();
+        // The last byte is the input count, replace it with the largest varint
+        bytes.pop();
+        bytes.extend_from_slice(&[0xff, 0xff, 0xff, 0xff, 0x0f]);
+
+        assert!(Replay::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn frame_overflow_is_an_error() {
+        let mut replay = Replay::new(1, Settings::default());
+        replay.inputs = vec![(u32::MAX, Input::Press(Action::HardDrop)), (u32::MAX, Input::Press(Action::HardDrop))];
+        let mut bytes = replay.to_bytes();
+        // Make the second delta nonzero so the frames add past u32::MAX
+        let last = bytes.len() - 2;
+        bytes[last] = 1;
+
+        assert!(Replay::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn invalid_settings_are_an_error() {
+        let settings = Settings { garbage_messiness: f32::NAN, ..Settings::default() };
+        assert!(Replay::from_bytes(&Replay::new(1, settings).to_bytes()).is_err());
+
+        let settings = Settings { lock_delay: f32::INFINITY, ..Settings::default() };
+        assert!(Replay::from_bytes(&Replay::new(1, settings).to_bytes()).is_err());
+
+        let settings = Settings { start_level: u32::MAX, ..Settings::default() };
+        assert!(Replay::from_bytes(&Replay::new(1, settings).to_bytes()).is_err());
+    }
+}
@@ -0,0 +1,145 @@
+// Super Rotation System. Offsets are [x, y] with y pointing up, in the order they are tried.
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RotationState {
+    Spawn,
+    Right,
+    Reverse,
+    Left
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Rotation {
+    Clockwise,
+    CounterClockwise,
+    Half
+}
+
+// Reported back by a successful rotation. 'index' is 0 when the piece
+// rotated in place and the position in the kick table otherwise.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Kick {
+    pub from: RotationState,
+    pub to: RotationState,
+    pub index: usize,
+    pub offset: [i32; 2]
+}
+
+// Offsets for every rotation, indexed by 'RotationState::index' of the starting state then 'Rotation::index'
+pub type Kicks = [[Vec<[i32; 2]>; 3]; 4];
+
+const NO_KICKS: &[[i32; 2]] = &[[0, 0]];
+
+// Indexed by 'RotationState::index' of the starting state, clockwise then counter-clockwise
+const JLSTZ_KICKS: [[&[[i32; 2]]; 2]; 4] = [
+    [
+        &[[0, 0], [-1, 0], [-1, 1], [0, -2], [-1, -2]], // 0 -> R
+        &[[0, 0], [1, 0], [1, 1], [0, -2], [1, -2]] // 0 -> L
+    ],
+    [
+        &[[0, 0], [1, 0], [1, -1], [0, 2], [1, 2]], // R -> 2
+        &[[0, 0], [1, 0], [1, -1], [0, 2], [1, 2]] // R -> 0
+    ],
+    [
+        &[[0, 0], [1, 0], [1, 1], [0, -2], [1, -2]], // 2 -> L
+        &[[0, 0], [-1, 0], [-1, 1], [0, -2], [-1, -2]] // 2 -> R
+    ],
+    [
+        &[[0, 0], [-1, 0], [-1, -1], [0, 2], [-1, 2]], // L -> 0
+        &[[0, 0], [-1, 0], [-1, -1], [0, 2], [-1, 2]] // L -> 2
+    ]
+];
+
+const I_KICKS: [[&[[i32; 2]]; 2]; 4] = [
+    [
+        &[[0, 0], [-2, 0], [1, 0], [-2, -1], [1, 2]], // 0 -> R
+        &[[0, 0], [-1, 0], [2, 0], [-1, 2], [2, -1]] // 0 -> L
+    ],
+    [
+        &[[0, 0], [-1, 0], [2, 0], [-1, 2], [2, -1]], // R -> 2
+        &[[0, 0], [2, 0], [-1, 0], [2, 1], [-1, -2]] // R -> 0
+    ],
+    [
+        &[[0, 0], [2, 0], [-1, 0], [2, 1], [-1, -2]], // 2 -> L
+        &[[0, 0], [1, 0], [-2, 0], [1, -2], [-2, 1]] // 2 -> R
+    ],
+    [
+        &[[0, 0], [1, 0], [-2, 0], [1, -2], [-2, 1]], // L -> 0
+        &[[0, 0], [-2, 0], [1, 0], [-2, -1], [1, 2]] // L -> 2
+    ]
+];
+
+// The guideline has no 180 rotation, these follow the common SRS+ extension
+const HALF_KICKS: [&[[i32; 2]]; 4] = [
+    &[[0, 0], [0, 1], [1, 1], [-1, 1], [1, 0], [-1, 0]], // 0 -> 2
+    &[[0, 0], [1, 0], [1, 2], [1, 1], [0, 2], [0, 1]], // R -> L
+    &[[0, 0], [0, -1], [-1, -1], [1, -1], [-1, 0], [1, 0]], // 2 -> 0
+    &[[0, 0], [-1, 0], [-1, 2], [-1, 1], [0, 2], [0, 1]] // L -> R
+];
+
+impl RotationState {
+    pub fn rotate(self, rotation: Rotation) -> Self {
+        let turns = match rotation {
+            Rotation::Clockwise => 1,
+            Rotation::Half => 2,
+            Rotation::CounterClockwise => 3
+        };
+
+        match (self.index() + turns) % 4 {
+            0 => RotationState::Spawn,
+            1 => RotationState::Right,
+            2 => RotationState::Reverse,
+            _ => RotationState::Left
+        }
+    }
+
+    pub fn index(self) -> usize {
+        match self {
+            RotationState::Spawn => 0,
+            RotationState::Right => 1,
+            RotationState::Reverse => 2,
+            RotationState::Left => 3
+        }
+    }
+}
+
+impl Rotation {
+    pub fn index(self) -> usize {
+        match self {
+            Rotation::Clockwise => 0,
+            Rotation::CounterClockwise => 1,
+            Rotation::Half => 2
+        }
+    }
+}
+
+// The kick tables piece sets can name: 'srs' for J, L, S, T and Z, 'srs-i' for I and 'none'
+pub fn kick_preset(name: &str) -> Option<Kicks> {
+    let table = match name {
+        "srs" => &JLSTZ_KICKS,
+        "srs-i" => &I_KICKS,
+        "none" => return Some(std::array::from_fn(|_| std::array::from_fn(|_| NO_KICKS.to_vec()))),
+        _ => return None
+    };
+
+    Some(std::array::from_fn(|from| [table[from][0].to_vec(), table[from][1].to_vec(), HALF_KICKS[from].to_vec()]))
+}
+
+// Rotates a square bounding box about its centre
+pub fn rotate_cells(cells: &[Vec<bool>], rotation: Rotation) -> Vec<Vec<bool>> {
+    let size = cells.len();
+    let mut rotated = vec![vec![false; size]; size];
+
+    for (y, row) in cells.iter().enumerate() {
+        for (x, val) in row.iter().enumerate() {
+            let (new_x, new_y) = match rotation {
+                Rotation::Clockwise => (y, size - 1 - x),
+                Rotation::CounterClockwise => (size - 1 - y, x),
+                Rotation::Half => (size - 1 - x, size - 1 - y)
+            };
+            rotated[new_y][new_x] = *val;
+        }
+    }
+
+    rotated
+}
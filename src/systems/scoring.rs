@@ -0,0 +1,191 @@
+use super::spin::Spin;
+
+// Guideline scoring. Line clears are worth more at higher levels, and the level
+// goes up every 'LINES_PER_LEVEL' lines.
+
+pub const LINES_PER_LEVEL: u32 = 10;
+
+// Indexed by lines cleared
+const LINE_CLEAR_POINTS: [u64; 5] = [0, 100, 300, 500, 800];
+const MINI_SPIN_POINTS: [u64; 3] = [100, 200, 400];
+const SPIN_POINTS: [u64; 4] = [400, 800, 1200, 1600];
+const PERFECT_CLEAR_POINTS: [u64; 5] = [0, 800, 1200, 1800, 2000];
+const BACK_TO_BACK_PERFECT_TETRIS_POINTS: u64 = 3200;
+const COMBO_POINTS: u64 = 50;
+const SOFT_DROP_POINTS: u64 = 1;
+const HARD_DROP_POINTS: u64 = 2;
+
+// What a single piece locking was worth
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ClearResult {
+    pub lines: usize,
+    pub spin: Spin,
+    // Consecutive clearing locks before this one, 0 for the first clear in a chain
+    pub combo: u32,
+    // A tetris or spin clear following another with no plain clear in between
+    pub back_to_back: bool,
+    pub perfect_clear: bool,
+    pub points: u64
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Scoring {
+    pub score: u64,
+    pub level: u32,
+    pub lines: u32,
+    // Garbage rows cleared, counted on top of 'lines'
+    pub garbage_cleared: u32,
+    start_level: u32,
+    // Clearing locks in a row, reset by a lock that clears nothing
+    streak: u32,
+    // Whether the last clear was a tetris or a spin
+    difficult: bool
+}
+
+impl Scoring {
+    pub fn new(start_level: u32) -> Self {
+        let start_level = start_level.max(1);
+
+        Scoring {
+            score: 0,
+            level: start_level,
+            lines: 0,
+            garbage_cleared: 0,
+            start_level,
+            streak: 0,
+            difficult: false
+        }
+    }
+
+    pub fn lock(&mut self, count: usize, spin: Spin, perfect_clear: bool) -> ClearResult {
+        let mut points = match spin {
+            Spin::None => LINE_CLEAR_POINTS[count.min(4)],
+            // Minis past a double, only possible with all-spin, never score below the plain clear
+            Spin::Mini => MINI_SPIN_POINTS[count.min(2)].max(LINE_CLEAR_POINTS[count.min(4)]),
+            Spin::Full => SPIN_POINTS[count.min(3)]
+        };
+
+        let mut back_to_back = false;
+
+        if count > 0 {
+            let difficult = count >= 4 || spin != Spin::None;
+            back_to_back = difficult && self.difficult;
+            self.difficult = difficult;
+            self.streak += 1;
+
+            if back_to_back {
+                points += points / 2;
+            }
+
+            if perfect_clear {
+                points += if back_to_back && count >= 4 {
+                    BACK_TO_BACK_PERFECT_TETRIS_POINTS
+                } else {
+                    PERFECT_CLEAR_POINTS[count.min(4)]
+                };
+            }
+
+            points += COMBO_POINTS * self.streak.saturating_sub(1) as u64;
+        } else {
+            self.streak = 0;
+        }
+
+        let result = ClearResult {
+            lines: count,
+            spin,
+            combo: self.streak.saturating_sub(1),
+            back_to_back,
+            perfect_clear,
+            points: points * self.level as u64
+        };
+
+        self.score += result.points;
+        self.lines += count as u32;
+        self.level = self.level.max(self.start_level.saturating_add(self.lines / LINES_PER_LEVEL));
+
+        result
+    }
+
+    pub fn start_level(&self) -> u32 {
+        self.start_level
+    }
+
+    pub fn soft_drop(&mut self, cells: i32) {
+        self.score += SOFT_DROP_POINTS * cells as u64;
+    }
+
+    pub fn hard_drop(&mut self, cells: i32) {
+        self.score += HARD_DROP_POINTS * cells as u64;
+    }
+
+    // Seconds per row at the current level: (0.8 - (level - 1) * 0.007) ^ (level - 1)
+    pub fn gravity(&self) -> f32 {
+        let level = (self.level - 1) as f32;
+        (0.8 - level * 0.007).max(0.0).powf(level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(count: usize, spin: Spin) -> u64 {
+        Scoring::new(1).lock(count, spin, false).points
+    }
+
+    #[test]
+    fn minis_score_at_least_the_plain_clear() {
+        assert_eq!(points(0, Spin::Mini), 100);
+        assert_eq!(points(1, Spin::Mini), 200);
+        assert_eq!(points(2, Spin::Mini), 400);
+        assert_eq!(points(3, Spin::Mini), points(3, Spin::None));
+        assert_eq!(points(4, Spin::Mini), points(4, Spin::None));
+    }
+
+    #[test]
+    fn high_start_levels_dont_overflow() {
+        let mut scoring = Scoring::new(u32::MAX);
+        for _ in 0..3 {
+            scoring.lock(4, Spin::None, false);
+        }
+        assert_eq!(scoring.level, u32::MAX);
+    }
+
+    #[test]
+    fn combos_grow_until_a_lock_clears_nothing() {
+        let mut scoring = Scoring::new(1);
+        assert_eq!(scoring.lock(1, Spin::None, false).combo, 0);
+
+        let result = scoring.lock(1, Spin::None, false);
+        assert_eq!((result.combo, result.points), (1, 150));
+
+        scoring.lock(0, Spin::None, false);
+        assert_eq!(scoring.lock(1, Spin::None, false).combo, 0);
+    }
+
+    #[test]
+    fn back_to_back_needs_unbroken_difficult_clears() {
+        let mut scoring = Scoring::new(1);
+        assert!(!scoring.lock(4, Spin::None, false).back_to_back);
+
+        // Half as much again, plus the combo
+        let result = scoring.lock(4, Spin::None, false);
+        assert_eq!((result.back_to_back, result.points), (true, 1250));
+
+        // A lock that clears nothing keeps the chain, a plain clear breaks it
+        scoring.lock(0, Spin::None, false);
+        assert!(scoring.lock(2, Spin::Full, false).back_to_back);
+        scoring.lock(1, Spin::None, false);
+        assert!(!scoring.lock(4, Spin::None, false).back_to_back);
+    }
+
+    #[test]
+    fn perfect_clears_score_a_bonus() {
+        assert_eq!(Scoring::new(1).lock(1, Spin::None, true).points, 900);
+
+        let mut scoring = Scoring::new(1);
+        scoring.lock(4, Spin::None, false);
+        // A back to back perfect tetris replaces the usual bonus
+        assert_eq!(scoring.lock(4, Spin::None, true).points, 1200 + 3200 + 50);
+    }
+}
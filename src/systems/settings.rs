@@ -0,0 +1,136 @@
+use std::{ops::RangeInclusive, sync::Arc};
+
+use super::{generator::Randomizer, mode::Mode, pieces::PieceSet, FRAME_TIME};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum HoldMode {
+    Disabled,
+    // Once per piece, refreshed when a piece locks
+    #[default]
+    Once,
+    Infinite
+}
+
+impl HoldMode {
+    pub const ALL: [HoldMode; 3] = [HoldMode::Disabled, HoldMode::Once, HoldMode::Infinite];
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum LockReset {
+    // Any move or rotation restarts the lock timer, up to 'MAX_LOCK_RESETS' times
+    #[default]
+    Move,
+    // Only falling to a new lowest row restarts the lock timer
+    Step,
+    Never
+}
+
+impl LockReset {
+    pub const ALL: [LockReset; 3] = [LockReset::Move, LockReset::Step, LockReset::Never];
+}
+
+pub const MAX_PREVIEW: usize = 7;
+pub const MAX_LOCK_RESETS: u32 = 15;
+// Board sizes that play and draw sensibly, the narrowest fits an I piece on its side
+pub const BOARD_WIDTHS: RangeInclusive<u32> = 4..=40;
+pub const BOARD_HEIGHTS: RangeInclusive<u32> = 4..=60;
+pub const MAX_HIDDEN_ROWS: u32 = 40;
+// Past this the gravity curve is already dropping pieces many rows a frame
+pub const MAX_START_LEVEL: u32 = 30;
+// Seconds a fading stack takes to disappear unless told otherwise
+pub const FADE_SECONDS: f32 = 5.0;
+
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub mode: Mode,
+    // The pieces dealt, shared between games since sets don't change once loaded
+    pub pieces: Arc<PieceSet>,
+    // Columns and visible rows of the board
+    pub width: u32,
+    pub height: u32,
+    // Rows above the visible ones that pieces spawn into and can still move through
+    pub hidden_rows: u32,
+    pub randomizer: Randomizer,
+    pub hold: HoldMode,
+    // Number of upcoming pieces shown, up to 'MAX_PREVIEW'
+    pub preview: usize,
+    // Gravity multiplier while soft dropping, 'f32::INFINITY' drops straight to the floor
+    pub soft_drop: f32,
+    // Seconds a grounded piece waits before locking
+    pub lock_delay: f32,
+    pub lock_reset: LockReset,
+    // Seconds a direction is held before auto shift starts
+    pub das: f32,
+    // Seconds between auto shifts, 0 moves straight to the wall
+    pub arr: f32,
+    // Seconds auto shift is held off after a rotation
+    pub das_cut: f32,
+    // Seconds between a piece locking and the next one appearing
+    pub entry_delay: f32,
+    // Seconds cleared rows stay on the board before the stack above falls, before the entry delay
+    pub line_clear_delay: f32,
+    pub start_level: u32,
+    // Score pieces other than T as mini spins when rotated into a spot they can't move out of
+    pub all_spin: bool,
+    // Show where the active piece will land
+    pub ghost: bool,
+    // End the game when any part of a piece locks above the skyline, not just all of it
+    pub partial_lock_out: bool,
+    // Chance a garbage hole moves between rows that arrive together
+    pub garbage_messiness: f32,
+    // Chance a garbage hole moves between separate batches of garbage
+    pub garbage_change: f32,
+    // Seconds locked blocks take to fade from view, 0 hides them as they lock and 'None' always shows them
+    pub fade: Option<f32>
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            mode: Mode::default(),
+            pieces: Arc::new(PieceSet::standard()),
+            width: 10,
+            height: 20,
+            hidden_rows: 20,
+            randomizer: Randomizer::default(),
+            hold: HoldMode::default(),
+            preview: 5,
+            soft_drop: 20.0,
+            lock_delay: 0.5,
+            lock_reset: LockReset::default(),
+            das: 10.0 / 60.0,
+            arr: 2.0 / 60.0,
+            das_cut: 0.0,
+            entry_delay: 0.0,
+            line_clear_delay: 0.0,
+            start_level: 1,
+            all_spin: false,
+            ghost: true,
+            partial_lock_out: false,
+            garbage_messiness: 1.0,
+            garbage_change: 1.0,
+            fade: None
+        }
+    }
+}
+
+// The timings in effect right now, in seconds, and the lock reset rule they're played by.
+// They start out from 'Settings' and the level's gravity, and modes can replace them as the game goes on
+#[derive(Clone, Copy, Debug)]
+pub struct Timings {
+    // Seconds per row, anything under a step drops several rows at once
+    pub gravity: f32,
+    pub das: f32,
+    pub lock_delay: f32,
+    pub lock_reset: LockReset,
+    pub entry_delay: f32,
+    pub line_clear_delay: f32
+}
+
+impl Timings {
+    // 20G or faster, where pieces land the moment they appear. Gravity tables reach 20G
+    // through float division, so this allows for rounding
+    pub fn instant_gravity(&self) -> bool {
+        FRAME_TIME / self.gravity >= 20.0 - 1e-3
+    }
+}
@@ -0,0 +1,130 @@
+use super::Dir;
+
+// Delayed auto shift. Holding a direction moves once, waits 'das' seconds,
+// then repeats every 'arr' seconds. The most recently pressed direction wins.
+#[derive(Default)]
+pub struct AutoShift {
+    left: bool,
+    right: bool,
+    dir: Option<Dir>,
+    // Seconds the current direction has been held
+    charge: f32,
+    // Whether the first auto shift of the current hold has happened
+    shifting: bool,
+    // Seconds banked towards the next repeat, negative while cut
+    repeat: f32
+}
+
+impl AutoShift {
+    // Returns false for key repeats of a direction that is already held
+    pub fn press(&mut self, dir: Dir) -> bool {
+        let held = match dir {
+            Dir::Left => &mut self.left,
+            Dir::Right => &mut self.right
+        };
+        if *held { return false }
+
+        *held = true;
+        self.dir = Some(dir);
+        self.charge = 0.0;
+        self.shifting = false;
+        self.repeat = 0.0;
+        true
+    }
+
+    // Falls back to the other direction if it is still held
+    pub fn release(&mut self, dir: Dir) {
+        match dir {
+            Dir::Left => self.left = false,
+            Dir::Right => self.right = false
+        }
+
+        if self.dir == Some(dir) {
+            self.dir = if self.left {
+                Some(Dir::Left)
+            } else if self.right {
+                Some(Dir::Right)
+            } else {
+                None
+            };
+            self.charge = 0.0;
+            self.shifting = false;
+            self.repeat = 0.0;
+        }
+    }
+
+    pub fn dir(&self) -> Option<Dir> {
+        self.dir
+    }
+
+    // Builds up charge without shifting, used while there is no piece to move. A hold
+    // charged this way makes its first auto shift on the next piece's first frame
+    pub fn charge(&mut self, elapsed: f32) {
+        if self.dir.is_some() {
+            self.charge += elapsed;
+        }
+    }
+
+    // Holds off the next repeat by 'delay' seconds if already auto shifting
+    pub fn cut(&mut self, delay: f32) {
+        if self.dir.is_some() && self.shifting {
+            self.repeat = -delay;
+        }
+    }
+
+    // Number of cells to shift this frame, 'u32::MAX' with an ARR of 0
+    pub fn update(&mut self, elapsed: f32, das: f32, arr: f32) -> u32 {
+        if self.dir.is_none() { return 0 }
+
+        self.charge += elapsed;
+
+        if self.charge < das { return 0 }
+
+        let mut moves = 0;
+        if self.shifting {
+            self.repeat += elapsed;
+        } else {
+            // The first auto shift lands exactly on the DAS threshold, or right now if
+            // the threshold passed while there was no piece
+            let late = self.charge - das;
+            self.shifting = true;
+            self.repeat = if late < elapsed { late } else { 0.0 };
+            moves = 1;
+        }
+
+        if self.repeat < 0.0 { return moves }
+        if arr <= 0.0 { return u32::MAX }
+
+        let repeats = (self.repeat / arr) as u32;
+        self.repeat -= repeats as f32 * arr;
+        moves + repeats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offsets(shift: &mut AutoShift, frames: usize) -> Vec<u32> {
+        let mut offset = 0;
+        (0..frames).map(|_| {
+            offset += shift.update(0.25, 0.5, 0.5);
+            offset
+        }).collect()
+    }
+
+    #[test]
+    fn first_auto_shift_lands_on_das() {
+        let mut shift = AutoShift::default();
+        shift.press(Dir::Right);
+        assert_eq!(offsets(&mut shift, 6), [0, 1, 1, 2, 2, 3]);
+    }
+
+    #[test]
+    fn das_charged_without_a_piece_shifts_on_its_first_frame() {
+        let mut shift = AutoShift::default();
+        shift.press(Dir::Right);
+        shift.charge(1.0);
+        assert_eq!(offsets(&mut shift, 5), [1, 1, 2, 2, 3]);
+    }
+}
@@ -0,0 +1,36 @@
+use super::rotation::{Kick, RotationState};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Spin {
+    None,
+    Mini,
+    Full
+}
+
+// The T-spin triple kick, which always counts as a full T-spin
+const TST_KICK: usize = 4;
+
+// 3-corner rule. At least three corners of the T's 3x3 box must be occupied, and the spin
+// is only full if both corners either side of the point are, or the last kick was used.
+pub fn t_spin(pos: [i32; 2], kick: &Kick, occupied: impl Fn([i32; 2]) -> bool) -> Spin {
+    let corner = |x: i32, y: i32| occupied([pos[0] + x, pos[1] + y]);
+
+    let (bottom_left, bottom_right, top_left, top_right) = (corner(0, 0), corner(2, 0), corner(0, 2), corner(2, 2));
+
+    let (front, back) = match kick.to {
+        RotationState::Spawn => ([top_left, top_right], [bottom_left, bottom_right]),
+        RotationState::Right => ([top_right, bottom_right], [top_left, bottom_left]),
+        RotationState::Reverse => ([bottom_left, bottom_right], [top_left, top_right]),
+        RotationState::Left => ([top_left, bottom_left], [top_right, bottom_right])
+    };
+
+    let corners = front.iter().chain(back.iter()).filter(|c| **c).count();
+
+    if corners < 3 {
+        Spin::None
+    } else if front.iter().all(|c| *c) || kick.index == TST_KICK {
+        Spin::Full
+    } else {
+        Spin::Mini
+    }
+}